@@ -1,5 +1,11 @@
-use crossterm::event::KeyCode;
+use std::{
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
 
+use crate::{key::KeyChord, transfer::ConflictPolicy};
+
+#[derive(PartialEq, Clone)]
 pub enum Action {
     Close,
     Redraw,
@@ -10,12 +16,128 @@ pub enum Action {
     Home,
     End,
     ToggleHidden,
+    TogglePreview,
+    ToggleColumns,
+    ToggleDetails,
+    ToggleFollowLinks,
+    JumpToTarget,
+    CycleSort,
+    ReverseSort,
+    ToggleDirectoriesFirst,
+    ToggleDual,
+    SwitchPane,
+    SwapPanes,
+    SyncPane,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
     Rename,
+    BulkRename,
     Remove,
+    Delete,
+    ShowTrash,
+    Restore,
+    Purge,
     Add,
+    Link,
+    CycleLinkKind,
+    Complete,
+    EditPermissions,
     Open,
     Back,
     Search,
+    Find,
+    Grep,
+    CycleMatcher,
+    ToggleSmartCase,
     ToggleHelp,
-    Input(KeyCode),
+    ToggleMark,
+    MarkAll,
+    InvertMarks,
+    MarkGlob,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    Resolve(ConflictPolicy),
+    ResolveAll(ConflictPolicy),
+    Input(KeyChord),
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    /// Parses the name an action is bound by in the configuration.
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name {
+            "close" => Action::Close,
+            "redraw" => Action::Redraw,
+            "move_up" => Action::MoveUp,
+            "move_down" => Action::MoveDown,
+            "scroll_up" => Action::ScrollUp,
+            "scroll_down" => Action::ScrollDown,
+            "home" => Action::Home,
+            "end" => Action::End,
+            "toggle_hidden" => Action::ToggleHidden,
+            "toggle_preview" => Action::TogglePreview,
+            "toggle_columns" => Action::ToggleColumns,
+            "toggle_details" => Action::ToggleDetails,
+            "toggle_follow_links" => Action::ToggleFollowLinks,
+            "jump_to_target" => Action::JumpToTarget,
+            "cycle_sort" => Action::CycleSort,
+            "reverse_sort" => Action::ReverseSort,
+            "toggle_directories_first" => Action::ToggleDirectoriesFirst,
+            "toggle_dual" => Action::ToggleDual,
+            "switch_pane" => Action::SwitchPane,
+            "swap_panes" => Action::SwapPanes,
+            "sync_pane" => Action::SyncPane,
+            "new_tab" => Action::NewTab,
+            "close_tab" => Action::CloseTab,
+            "next_tab" => Action::NextTab,
+            "previous_tab" => Action::PreviousTab,
+            "rename" => Action::Rename,
+            "bulk_rename" => Action::BulkRename,
+            "remove" => Action::Remove,
+            "delete" => Action::Delete,
+            "show_trash" => Action::ShowTrash,
+            "restore" => Action::Restore,
+            "purge" => Action::Purge,
+            "add" => Action::Add,
+            "link" => Action::Link,
+            "cycle_link_kind" => Action::CycleLinkKind,
+            "complete" => Action::Complete,
+            "edit_permissions" => Action::EditPermissions,
+            "open" => Action::Open,
+            "back" => Action::Back,
+            "search" => Action::Search,
+            "find" => Action::Find,
+            "grep" => Action::Grep,
+            "cycle_matcher" => Action::CycleMatcher,
+            "toggle_smart_case" => Action::ToggleSmartCase,
+            "toggle_help" => Action::ToggleHelp,
+            "toggle_mark" => Action::ToggleMark,
+            "mark_all" => Action::MarkAll,
+            "invert_marks" => Action::InvertMarks,
+            "mark_glob" => Action::MarkGlob,
+            "copy" => Action::Copy,
+            "cut" => Action::Cut,
+            "paste" => Action::Paste,
+            "undo" => Action::Undo,
+            "redo" => Action::Redo,
+            "skip" => Action::Resolve(ConflictPolicy::Skip),
+            "overwrite" => Action::Resolve(ConflictPolicy::Overwrite),
+            "keep_both" => Action::Resolve(ConflictPolicy::KeepBoth),
+            "skip_all" => Action::ResolveAll(ConflictPolicy::Skip),
+            "overwrite_all" => Action::ResolveAll(ConflictPolicy::Overwrite),
+            "keep_both_all" => Action::ResolveAll(ConflictPolicy::KeepBoth),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown action `{}`", name),
+                ))
+            }
+        })
+    }
 }
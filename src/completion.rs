@@ -0,0 +1,222 @@
+use std::{
+    env,
+    fs::read_dir,
+    io::{stdout, Result},
+    path::Path,
+};
+
+use crossterm::{
+    cursor,
+    style::{self, Stylize},
+    terminal, QueueableCommand,
+};
+
+use crate::{name, text, theme::Theme, window::Window};
+
+/// Most candidates shown at once.
+const ROWS: usize = 8;
+
+/// Texts that could replace `typed`, a path relative to `directory`: the
+/// names of environment variables when it ends in `$NAME`, and otherwise the
+/// entries of the directory it leads to whose names start like its last
+/// component, written the way `name::display` shows them and directories
+/// with a trailing slash. Hidden entries are offered once the component
+/// starts with a dot. What comes before the last component is kept as typed,
+/// `~` and variables included.
+pub fn candidates(typed: &str, directory: &Path) -> Vec<String> {
+    if typed == "~" {
+        return vec!["~/".to_string()];
+    }
+
+    let (head, last) = typed.split_at(typed.rfind('/').map_or(0, |index| index + 1));
+
+    if let Some(start) = last.rfind('$') {
+        let prefix = &last[start + 1..];
+
+        if prefix.chars().all(is_variable) {
+            let mut names = env::vars_os()
+                .filter_map(|(name, _)| name.into_string().ok())
+                .filter(|name| name.starts_with(prefix))
+                .map(|name| format!("{}{}${}", head, &last[..start], name))
+                .collect::<Vec<_>>();
+
+            names.sort();
+            return names;
+        }
+    }
+
+    let Ok(entries) = name::parse(&expand(head)).and_then(|head| read_dir(directory.join(head)))
+    else {
+        return Vec::new();
+    };
+
+    let mut candidates = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = name::display(&entry.file_name());
+
+            if !name.starts_with(last) || (name.starts_with('.') && !last.starts_with('.')) {
+                return None;
+            }
+
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", head, name, slash))
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort();
+    candidates
+}
+
+/// Expands a leading `~` to the home directory and `$NAME` to the value of
+/// the variable, leaving those that are unset as typed. Values are written
+/// the way `name::display` writes names, so the result goes through
+/// `name::parse` like the rest of what was typed, and `\x7e` and `\x24`
+/// stand for a `~` and a `$` that are not expanded.
+pub fn expand(typed: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = typed;
+
+    if rest == "~" || rest.starts_with("~/") {
+        if let Some(home) = env::var_os("HOME") {
+            expanded.push_str(&name::display(&home));
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+
+        let after = &rest[start + 1..];
+        let end = after.find(|c| !is_variable(c)).unwrap_or(after.len());
+
+        match env::var_os(&after[..end]) {
+            Some(value) if end > 0 => {
+                expanded.push_str(&name::display(&value));
+                rest = &after[end..];
+            }
+            _ => {
+                expanded.push('$');
+                rest = after;
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+fn is_variable(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_'
+}
+
+/// The candidates of a completion that left a choice, shown in a window
+/// next to the prompt.
+pub struct Menu {
+    pub candidates: Vec<String>,
+    /// The candidate put in the prompt, once completing again has picked one.
+    pub selection: Option<usize>,
+}
+
+impl Menu {
+    /// Queues the menu below `anchor`, where the text of the prompt starts,
+    /// or above it when there is no room below.
+    pub fn draw(&self, anchor: (u16, u16), theme: &Theme) -> Result<()> {
+        let terminal_size = terminal::size()?;
+        let labels = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let trimmed = candidate.trim_end_matches('/');
+                &candidate[trimmed.rfind('/').map_or(0, |index| index + 1)..]
+            })
+            .collect::<Vec<_>>();
+
+        let rows = self.candidates.len().min(ROWS) as u16;
+        let widest = labels.iter().map(|label| text::width(label)).max();
+        let width =
+            (widest.unwrap_or(0) + 3).min(terminal_size.0.saturating_sub(1) as usize) as u16;
+        let x = anchor
+            .0
+            .saturating_sub(2)
+            .min(terminal_size.0.saturating_sub(width + 1));
+        let y = if anchor.1 + rows + 2 < terminal_size.1 {
+            anchor.1 + 1
+        } else {
+            anchor.1.saturating_sub(rows + 2)
+        };
+
+        let first = self
+            .selection
+            .map_or(0, |selection| selection.saturating_sub(ROWS - 1));
+
+        for (row, (index, label)) in labels
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows as usize)
+            .enumerate()
+        {
+            let label = text::fit(label, width.saturating_sub(3) as usize);
+            let line = format!(" {} ", label);
+
+            stdout()
+                .queue(cursor::MoveTo(x + 1, y + 1 + row as u16))?
+                .queue(style::PrintStyledContent(
+                    if self.selection == Some(index) {
+                        line.with(theme.accent).on(theme.selection).bold()
+                    } else {
+                        line.stylize()
+                    },
+                ))?;
+        }
+
+        Window::new((x, y), (width, rows + 1)).draw()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    #[test]
+    fn home_and_variables_are_expanded() {
+        let home = name::display(&env::var_os("HOME").unwrap());
+        let path = name::display(&env::var_os("PATH").unwrap());
+
+        assert_eq!(expand("~"), home);
+        assert_eq!(expand("~/notes"), format!("{}/notes", home));
+        assert_eq!(expand("a/~"), "a/~");
+        assert_eq!(expand("$PATH:x"), format!("{}:x", path));
+        assert_eq!(expand("$NFM_UNSET_VARIABLE/$"), "$NFM_UNSET_VARIABLE/$");
+        assert_eq!(expand(r"\x7e/\x24PATH"), r"\x7e/\x24PATH");
+    }
+
+    #[test]
+    fn candidates_start_like_the_last_component() {
+        let directory = env::temp_dir().join(format!("nfm-test-completion-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("alpha/inner")).unwrap();
+        fs::write(directory.join("alps.txt"), "").unwrap();
+        fs::write(directory.join(".alpine"), "").unwrap();
+        fs::write(directory.join(r"back\slash"), "").unwrap();
+
+        assert_eq!(candidates("al", &directory), ["alpha/", "alps.txt"]);
+        assert_eq!(candidates(".al", &directory), [".alpine"]);
+        assert_eq!(candidates("alpha/", &directory), ["alpha/inner/"]);
+        assert_eq!(
+            candidates(&format!("{}/alph", directory.display()), Path::new("/")),
+            [format!("{}/alpha/", directory.display())]
+        );
+        assert_eq!(candidates("back", &directory), [r"back\\slash"]);
+        assert_eq!(candidates(r"back\\s", &directory), [r"back\\slash"]);
+        assert!(candidates("missing/", &directory).is_empty());
+        assert_eq!(candidates("~", &directory), ["~/"]);
+        assert!(candidates("$PAT", &directory).contains(&"$PATH".to_string()));
+        assert!(candidates("x/$PAT", &directory).contains(&"x/$PATH".to_string()));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    io::{Error, ErrorKind, Result},
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    details::Details,
+    keymap::{parse_sequence, Bindings, Keymap, Preset},
+    layout::Layout,
+    mode::Mode,
+    name,
+    opener::Rule,
+    search::Search,
+    sort::Sort,
+    theme::Theme,
+    xdg,
+};
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub show_hidden: bool,
+    pub follow_links: bool,
+    pub layout: Layout,
+    pub sort: Sort,
+    pub details: Details,
+    pub search: Search,
+    pub opener: Vec<Rule>,
+    pub keys: Keys,
+    pub theme: Theme,
+}
+
+/// The `[keys]` table: a preset to start from, and per-mode tables such as
+/// `[keys.normal]` mapping key sequences to action names on top of it.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Keys {
+    pub preset: Preset,
+    #[serde(flatten, deserialize_with = "deserialize_bindings")]
+    pub modes: HashMap<Mode, Bindings>,
+}
+
+impl Config {
+    /// Loads `$XDG_CONFIG_HOME/nfm/config.toml`. A missing file yields the
+    /// defaults.
+    pub fn load() -> Result<Self> {
+        let Some(path) = xdg::config_home().map(|home| home.join("config.toml")) else {
+            return Ok(Self::default());
+        };
+
+        let source = match read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+
+        toml::from_str(&source).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", name::display(path.as_os_str()), error),
+            )
+        })
+    }
+}
+
+impl Keys {
+    pub fn keymap(self) -> Keymap {
+        let mut keymap = Keymap::new(self.preset);
+
+        for (mode, bindings) in self.modes {
+            for (sequence, action) in bindings {
+                keymap.bind(mode.clone(), sequence, action);
+            }
+        }
+
+        keymap
+    }
+}
+
+fn deserialize_bindings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<HashMap<Mode, Bindings>, D::Error> {
+    HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(mode, bindings)| {
+            let bindings = bindings
+                .into_iter()
+                .map(|(sequence, action)| Ok((parse_sequence(&sequence)?, action.parse()?)))
+                .collect::<Result<_>>()?;
+
+            Ok((mode, bindings))
+        })
+        .collect::<Result<_>>()
+        .map_err(serde::de::Error::custom)
+}
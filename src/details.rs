@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    fs::{read_link, Metadata},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    entry::Entry,
+    name, text,
+    time::DateTime,
+    users::{group_name, user_name},
+};
+
+/// Fewest columns the name is left with before metadata columns are dropped.
+const NAME_WIDTH: usize = 16;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    /// Type and mode bits, as in `drwxr-xr-x`.
+    Permissions,
+    Owner,
+    Group,
+    Size,
+    Modified,
+    Accessed,
+    /// When the inode last changed.
+    Changed,
+    Links,
+    Inode,
+    /// Where a symbolic link points.
+    Target,
+}
+
+impl Column {
+    fn is_numeric(self) -> bool {
+        matches!(self, Column::Size | Column::Links | Column::Inode)
+    }
+}
+
+/// The `[details]` table: whether the long listing is shown, and which
+/// columns it has, from left to right.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Details {
+    pub show: bool,
+    pub columns: Vec<Column>,
+}
+
+impl Default for Details {
+    fn default() -> Self {
+        Self {
+            show: false,
+            columns: vec![
+                Column::Permissions,
+                Column::Owner,
+                Column::Group,
+                Column::Size,
+                Column::Modified,
+            ],
+        }
+    }
+}
+
+impl Details {
+    /// Fills in the details of `entries` for a listing `width` columns wide,
+    /// aligned across entries. Columns that would leave the name too little
+    /// room are dropped, starting from the right.
+    pub fn fill(&self, entries: &mut [Entry], width: usize) {
+        if !self.show {
+            return;
+        }
+
+        let mut names = Names::default();
+        let cells = entries
+            .iter()
+            .map(|entry| {
+                let metadata = entry.base.metadata().ok();
+
+                self.columns
+                    .iter()
+                    .map(|column| match &metadata {
+                        Some(metadata) => cell(*column, entry, metadata, &mut names),
+                        None => "?".to_string(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = Vec::new();
+        let mut used = 0;
+
+        for (index, column) in self.columns.iter().enumerate() {
+            let column_width = cells
+                .iter()
+                .map(|row| text::width(&row[index]))
+                .max()
+                .unwrap_or(0);
+
+            if used + column_width + 1 + NAME_WIDTH + 4 > width {
+                break;
+            }
+
+            used += column_width + 1;
+            widths.push((*column, column_width));
+        }
+
+        for (entry, row) in entries.iter_mut().zip(cells) {
+            entry.details = widths
+                .iter()
+                .zip(row)
+                .map(|((column, width), cell)| {
+                    let padding = " ".repeat(width - text::width(&cell));
+
+                    if column.is_numeric() {
+                        format!(" {}{}", padding, cell)
+                    } else {
+                        format!(" {}{}", cell, padding)
+                    }
+                })
+                .collect();
+        }
+    }
+}
+
+/// User and group names looked up so far, by id.
+#[derive(Default)]
+struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    fn user(&mut self, uid: u32) -> String {
+        self.users
+            .entry(uid)
+            .or_insert_with(|| user_name(uid).unwrap_or_else(|| uid.to_string()))
+            .clone()
+    }
+
+    fn group(&mut self, gid: u32) -> String {
+        self.groups
+            .entry(gid)
+            .or_insert_with(|| group_name(gid).unwrap_or_else(|| gid.to_string()))
+            .clone()
+    }
+}
+
+fn cell(column: Column, entry: &Entry, metadata: &Metadata, names: &mut Names) -> String {
+    match column {
+        Column::Permissions => permissions(metadata),
+        Column::Owner => names.user(metadata.uid()),
+        Column::Group => names.group(metadata.gid()),
+        Column::Size if metadata.is_dir() => "-".to_string(),
+        Column::Size => human_size(metadata.len()),
+        Column::Modified => metadata.modified().map(time).unwrap_or_default(),
+        Column::Accessed => metadata.accessed().map(time).unwrap_or_default(),
+        Column::Changed => time(from_seconds(metadata.ctime())),
+        Column::Links => metadata.nlink().to_string(),
+        Column::Inode => metadata.ino().to_string(),
+        Column::Target => read_link(entry.base.path())
+            .map(|target| format!("-> {}", name::display(target.as_os_str())))
+            .unwrap_or_default(),
+    }
+}
+
+/// Formats the type and mode bits the way `ls -l` does.
+fn permissions(metadata: &Metadata) -> String {
+    let file_type = metadata.file_type();
+
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        '-'
+    };
+
+    format!("{}{}", kind, mode_bits(metadata.mode()))
+}
+
+/// Formats the nine mode bits of `mode` as in `rwxr-xr-x`, with setuid,
+/// setgid and the sticky bit in the execute slots.
+pub fn mode_bits(mode: u32) -> String {
+    let bit = |mask: u32, character: char| {
+        if mode & mask != 0 {
+            character
+        } else {
+            '-'
+        }
+    };
+
+    // The execute slot also shows setuid, setgid and the sticky bit, in
+    // uppercase when the matching execute bit is not set.
+    let special = |execute: u32, special: u32, character: char| match (
+        mode & execute != 0,
+        mode & special != 0,
+    ) {
+        (true, true) => character,
+        (false, true) => character.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    };
+
+    [
+        bit(0o400, 'r'),
+        bit(0o200, 'w'),
+        special(0o100, 0o4000, 's'),
+        bit(0o040, 'r'),
+        bit(0o020, 'w'),
+        special(0o010, 0o2000, 's'),
+        bit(0o004, 'r'),
+        bit(0o002, 'w'),
+        special(0o001, 0o1000, 't'),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Formats `size` in bytes with a binary unit, as in `4.0K` or `12M`.
+fn human_size(size: u64) -> String {
+    const UNITS: [char; 6] = ['K', 'M', 'G', 'T', 'P', 'E'];
+
+    if size < 1024 {
+        return format!("{}B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+fn time(time: SystemTime) -> String {
+    let time = DateTime::local(time);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        time.year, time.month, time.day, time.hour, time.minute
+    )
+}
+
+fn from_seconds(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
@@ -1,16 +1,36 @@
-use std::{fs::DirEntry, io::{stdout, Result}, os::unix::fs::MetadataExt};
+use std::{
+    fs::{metadata, read_link, DirEntry},
+    io::{stdout, Result},
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+};
 
-use crossterm::{style::{self, StyledContent, Stylize}, terminal, QueueableCommand};
+use crossterm::{
+    style::{self, StyledContent, Stylize},
+    QueueableCommand,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{name, text, theme::Theme};
 
 #[derive(PartialEq, Clone)]
 pub enum EntryMark {
     Normal,
+    Selected,
     Removal,
 }
 
 pub struct Entry {
     pub base: DirEntry,
     pub mark: EntryMark,
+    /// Indices of the characters of the name matched by the search.
+    pub highlights: Vec<usize>,
+    /// The columns of the long listing, drawn after the name.
+    pub details: String,
+    /// Draw a symbolic link with the icon of what it points to rather than
+    /// as a link.
+    pub follow_links: bool,
 }
 
 impl Entry {
@@ -18,6 +38,9 @@ impl Entry {
         Self {
             base,
             mark: EntryMark::Normal,
+            highlights: Vec::new(),
+            details: String::new(),
+            follow_links: false,
         }
     }
 
@@ -29,17 +52,41 @@ impl Entry {
         }
     }
 
-    fn get_draw_prefix(&self) -> Result<StyledContent<&str>> {
+    fn get_draw_prefix(&self, theme: &Theme) -> Result<StyledContent<&str>> {
         match self.mark {
             EntryMark::Normal => Ok(" ".stylize()),
-            EntryMark::Removal => Ok("R".red()),
+            EntryMark::Selected => Ok("*".with(theme.accent).bold()),
+            EntryMark::Removal => Ok("R".with(theme.danger)),
+        }
+    }
+
+    /// Where the entry points if it is a symbolic link, and whether there is
+    /// nothing there.
+    fn link(&self) -> Option<(PathBuf, bool)> {
+        if !self.base.file_type().ok()?.is_symlink() {
+            return None;
         }
+
+        let target = read_link(self.base.path()).ok()?;
+        Some((target, metadata(self.base.path()).is_err()))
     }
 
     fn get_draw_icon(&self) -> Result<StyledContent<&str>> {
         let file_name = self.base.file_name();
-        let file_name_as_str = file_name.to_str().unwrap();
-        let file_type = self.base.file_type()?;
+        let file_name_as_str = &*file_name.to_string_lossy();
+        let mut file_type = self.base.file_type()?;
+        let mut target = None;
+
+        if file_type.is_symlink() {
+            match metadata(self.base.path()) {
+                Err(_) => return Ok("󰌸".red()),
+                Ok(_) if !self.follow_links => return Ok("".cyan()),
+                Ok(metadata) => {
+                    file_type = metadata.file_type();
+                    target = Some(metadata);
+                }
+            }
+        }
 
         if file_type.is_file() {
             if file_name_as_str.ends_with(".txt") {
@@ -90,7 +137,7 @@ impl Entry {
                 Ok("".dark_blue())
             } else if file_name_as_str.ends_with(".js") {
                 Ok("".yellow())
-            } else if self.base.metadata()?.mode() & 0777 != 0 {
+            } else if target.map_or_else(|| self.base.metadata(), Ok)?.mode() & 0o111 != 0 {
                 Ok("".red())
             } else {
                 Ok("".grey())
@@ -102,28 +149,83 @@ impl Entry {
         }
     }
 
-    pub fn draw(&self, selection: u16, index: usize) -> Result<()> {
-        stdout().queue(style::PrintStyledContent(
-            if selection == index as u16 {
-                format!(
-                    "{}{}  {:<width$}",
-                    self.get_draw_prefix()?,
-                    self.get_draw_icon()?,
-                    self.base.file_name().to_str().unwrap(),
-                    width = terminal::size()?.0 as usize - 4
-                )
-                .on_black()
-                .bold()
+    /// Draws the entry on the current line, within `width` columns.
+    pub fn draw(&self, selection: u16, index: usize, width: usize, theme: &Theme) -> Result<()> {
+        let selected = selection == index as u16;
+        let style = |content: StyledContent<String>| {
+            if selected {
+                content.on(theme.selection).bold()
+            } else {
+                content
+            }
+        };
+
+        let name = name::display(&self.base.file_name());
+
+        stdout().queue(style::PrintStyledContent(style(
+            format!(
+                "{}{}  ",
+                self.get_draw_prefix(theme)?,
+                self.get_draw_icon()?
+            )
+            .stylize(),
+        )))?;
+
+        let width = width.saturating_sub(4 + text::width(&self.details));
+        let link = self.link();
+        let broken = link.as_ref().is_some_and(|(_, broken)| *broken);
+        let mut used = 0;
+        let mut index = 0;
+
+        // Highlights count characters, and a grapheme cluster is highlighted
+        // when any of its characters is.
+        for grapheme in name.graphemes(true) {
+            let grapheme_width = text::width(grapheme);
+
+            if used + grapheme_width > width {
+                break;
+            }
+
+            let characters = grapheme.chars().count();
+            let highlighted =
+                (index..index + characters).any(|index| self.highlights.contains(&index));
+            let content = grapheme.to_string().stylize();
+
+            index += characters;
+
+            stdout().queue(style::PrintStyledContent(style(if highlighted {
+                content.with(theme.accent).underlined()
+            } else if broken {
+                content.with(theme.danger)
             } else {
-                format!(
-                    "{}{}  {}",
-                    self.get_draw_prefix()?,
-                    self.get_draw_icon()?,
-                    self.base.file_name().to_str().unwrap(),
-                )
-                .stylize()
-            },
-        ))?;
+                content
+            })))?;
+
+            used += grapheme_width;
+        }
+
+        if let Some((target, broken)) = link {
+            let target = format!(" -> {}", name::display(target.as_os_str()));
+            let target = text::truncate(&target, width - used).to_string();
+
+            used += text::width(&target);
+
+            stdout().queue(style::PrintStyledContent(style(if broken {
+                target.with(theme.danger).italic()
+            } else {
+                target.with(theme.muted)
+            })))?;
+        }
+
+        if selected || !self.details.is_empty() {
+            stdout().queue(style::PrintStyledContent(style(
+                " ".repeat(width.saturating_sub(used)).stylize(),
+            )))?;
+        }
+
+        stdout().queue(style::PrintStyledContent(style(
+            self.details.clone().with(theme.muted),
+        )))?;
 
         Ok(())
     }
@@ -0,0 +1,142 @@
+/// Matches `text` against a shell-style glob `pattern`.
+///
+/// Supports `*`, `?` and bracket classes such as `[abc]`, `[a-z]` and `[!0-9]`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+
+            Some('?') => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+
+            Some('[') => {
+                if let Some((matched, next)) = match_class(&pattern, p, text[t]) {
+                    if matched {
+                        p = next;
+                        t += 1;
+                        continue;
+                    }
+                } else if text[t] == '[' {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+            }
+
+            Some(&character) if character == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+
+            _ => {}
+        }
+
+        match backtrack {
+            Some((star, position)) => {
+                p = star + 1;
+                t = position + 1;
+                backtrack = Some((star, position + 1));
+            }
+
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Tries to match `character` against the bracket class starting at `start`.
+/// Returns whether it matched and the index right after the class, or `None`
+/// if the class is never closed.
+fn match_class(pattern: &[char], start: usize, character: char) -> Option<(bool, usize)> {
+    let mut index = start + 1;
+    let negated = matches!(pattern.get(index), Some('!') | Some('^'));
+
+    if negated {
+        index += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    while let Some(&current) = pattern.get(index) {
+        if current == ']' && !first {
+            return Some((matched != negated, index + 1));
+        }
+
+        first = false;
+
+        if pattern.get(index + 1) == Some(&'-') && pattern.get(index + 2).is_some_and(|&c| c != ']')
+        {
+            if (current..=pattern[index + 2]).contains(&character) {
+                matched = true;
+            }
+
+            index += 3;
+        } else {
+            if current == character {
+                matched = true;
+            }
+
+            index += 1;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes_match_listed_characters() {
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(!matches("[abc].txt", "d.txt"));
+        assert!(!matches("[abc].txt", "ab.txt"));
+    }
+
+    #[test]
+    fn classes_match_ranges() {
+        assert!(matches("file[0-9]", "file7"));
+        assert!(!matches("file[0-9]", "filex"));
+        assert!(matches("[a-cx-z]*", "yes"));
+        assert!(!matches("[a-cx-z]*", "no"));
+    }
+
+    #[test]
+    fn classes_can_be_negated() {
+        assert!(matches("[!0-9]*", "a1"));
+        assert!(!matches("[!0-9]*", "1a"));
+        assert!(matches("[^.]*", "visible"));
+        assert!(!matches("[^.]*", ".hidden"));
+    }
+
+    #[test]
+    fn leading_bracket_and_dash_are_literal() {
+        assert!(matches("[]]", "]"));
+        assert!(matches("[!]]", "a"));
+        assert!(!matches("[!]]", "]"));
+        assert!(matches("[a-]", "-"));
+    }
+
+    #[test]
+    fn unclosed_class_matches_a_bracket() {
+        assert!(matches("[ab", "[ab"));
+        assert!(!matches("[ab", "a"));
+    }
+}
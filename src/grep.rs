@@ -0,0 +1,164 @@
+use std::{
+    fmt::Write,
+    fs::File,
+    io::{BufRead, BufReader, Error, ErrorKind, Read, Result},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use ignore::DirEntry;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    mime,
+    search::{MatcherKind, Search},
+};
+
+/// How much of a file is looked at to tell text from binary.
+const HEAD: usize = 8192;
+
+/// Longest snippet kept for a matching line, in characters.
+const SNIPPET: usize = 256;
+
+pub struct GrepMatch {
+    /// Relative to the directory being searched.
+    pub path: PathBuf,
+    pub line: usize,
+    /// The line with leading whitespace removed, cut down to the part
+    /// around the match when it is long, and control characters escaped.
+    pub snippet: String,
+    /// Indices of the matched characters of the snippet.
+    pub highlights: Vec<usize>,
+}
+
+/// Compiles `query` for content search. It is a regex with the regex
+/// matcher and plain text with any other, and follows the smart case
+/// setting.
+pub fn pattern(search: &Search, query: &str) -> Result<Regex> {
+    let source = if search.matcher == MatcherKind::Regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+
+    RegexBuilder::new(&source)
+        .case_insensitive(!(search.smart_case && query.chars().any(char::is_uppercase)))
+        .build()
+        .map_err(|error| Error::new(ErrorKind::InvalidInput, error.to_string()))
+}
+
+/// The lines of the file at `entry` matching `pattern`. Only regular files
+/// are read, and those that cannot be or are binary yield nothing.
+pub fn search(entry: &DirEntry, relative: &Path, pattern: &Regex) -> Vec<GrepMatch> {
+    if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+        return Vec::new();
+    }
+
+    let Ok(file) = File::open(entry.path()) else {
+        return Vec::new();
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut head = Vec::new();
+
+    if (&mut reader)
+        .take(HEAD as u64)
+        .read_to_end(&mut head)
+        .is_err()
+        || !mime::is_text(&head)
+    {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+
+    for (index, line) in head.as_slice().chain(reader).split(b'\n').enumerate() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_start().trim_end_matches('\r');
+
+        let Some(found) = pattern.find(line) else {
+            continue;
+        };
+
+        // Long lines are cut down to a snippet that shows the match.
+        let start = line[..found.start()].chars().count();
+        let end = start + found.as_str().chars().count();
+        let skip = if end > SNIPPET {
+            start.saturating_sub(SNIPPET / 4)
+        } else {
+            0
+        };
+
+        let (snippet, highlights) = escape(
+            line.chars().skip(skip).take(SNIPPET),
+            start - skip..(end - skip).min(SNIPPET),
+        );
+
+        matches.push(GrepMatch {
+            path: relative.to_path_buf(),
+            line: index + 1,
+            snippet,
+            highlights,
+        });
+    }
+
+    matches
+}
+
+/// Writes control characters the way `name::display` does, so a line cannot
+/// move the cursor or change colors when drawn, and tabs as a space. Returns
+/// the text along with the indices its characters at `highlighted` ended up
+/// at.
+fn escape(
+    characters: impl Iterator<Item = char>,
+    highlighted: Range<usize>,
+) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut highlights = Vec::new();
+    let mut length = 0;
+
+    for (index, character) in characters.enumerate() {
+        let start = length;
+
+        if character == '\t' {
+            text.push(' ');
+            length += 1;
+        } else if character.is_control() {
+            let mut buffer = [0; 4];
+
+            for byte in character.encode_utf8(&mut buffer).bytes() {
+                let _ = write!(text, "\\x{:02x}", byte);
+                length += 4;
+            }
+        } else {
+            text.push(character);
+            length += 1;
+        }
+
+        if highlighted.contains(&index) {
+            highlights.extend(start..length);
+        }
+    }
+
+    (text, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_are_escaped() {
+        let (text, highlights) = escape("a\x1b[31mb\tc\u{85}d".chars(), 1..5);
+
+        assert_eq!(text, "a\\x1b[31mb c\\xc2\\x85d");
+        assert_eq!(highlights, (1..8).collect::<Vec<_>>());
+
+        let (_, highlights) = escape("\u{85}é".chars(), 1..2);
+        assert_eq!(highlights, vec![8]);
+    }
+}
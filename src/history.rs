@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+
+use crate::{line_editor::LineEditor, name, xdg};
+
+/// Most lines kept for each prompt.
+const LENGTH: usize = 100;
+
+/// What was entered at each prompt, oldest first, kept in
+/// `$XDG_STATE_HOME/nfm/history.toml`.
+#[derive(Default)]
+pub struct History {
+    prompts: BTreeMap<String, Vec<String>>,
+}
+
+impl History {
+    fn path() -> Option<PathBuf> {
+        xdg::state_home().map(|home| home.join("history.toml"))
+    }
+
+    /// Loads the history. A missing file yields an empty one.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let source = match read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+
+        let prompts = toml::from_str(&source).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", name::display(path.as_os_str()), error),
+            )
+        })?;
+
+        Ok(Self { prompts })
+    }
+
+    pub fn entries(&self, prompt: &str) -> &[String] {
+        self.prompts.get(prompt).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds the text of `editor` to the history of its prompt, moving it to
+    /// the end if it was there already, and saves the whole history. Saving
+    /// is best effort, so that a history that cannot be written never holds
+    /// up what was entered.
+    pub fn record(&mut self, editor: &LineEditor) {
+        if editor.is_empty() {
+            return;
+        }
+
+        let entries = self.prompts.entry(editor.name.to_string()).or_default();
+        entries.retain(|entry| entry != editor.text());
+        entries.push(editor.text().to_string());
+
+        if entries.len() > LENGTH {
+            entries.drain(..entries.len() - LENGTH);
+        }
+
+        let _ = self.save();
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let source = toml::to_string(&self.prompts)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()))?;
+
+        write(path, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+    use crate::key::KeyChord;
+
+    #[test]
+    fn prompts_browse_their_own_history() {
+        let history = History {
+            prompts: BTreeMap::from([
+                (
+                    "add".to_string(),
+                    vec!["one".to_string(), "two".to_string()],
+                ),
+                ("link".to_string(), vec!["elsewhere".to_string()]),
+            ]),
+        };
+        let key = |code| KeyChord::new(code, KeyModifiers::NONE);
+
+        let mut editor = LineEditor::new("add");
+        editor.set("draft".to_string());
+
+        editor.edit(key(KeyCode::Up), &history);
+        assert_eq!(editor.text(), "two");
+        editor.edit(key(KeyCode::Up), &history);
+        editor.edit(key(KeyCode::Up), &history);
+        assert_eq!(editor.text(), "one");
+
+        editor.edit(key(KeyCode::Down), &history);
+        assert_eq!(editor.text(), "two");
+        editor.edit(key(KeyCode::Down), &history);
+        assert_eq!(editor.text(), "draft");
+        editor.edit(key(KeyCode::Down), &history);
+        assert_eq!(editor.text(), "draft");
+
+        let mut editor = LineEditor::new("search");
+        editor.edit(key(KeyCode::Up), &history);
+        assert!(editor.is_empty());
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    fs::{create_dir, read_dir, remove_dir, remove_file, rename, symlink_metadata, File},
+    io::{Error, ErrorKind, Result},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    link::{self, LinkKind},
+    name,
+    transfer::{copy_recursive, move_path},
+    trash::{Trash, TrashItem},
+};
+
+/// Tells the entry an operation produced apart from one put at the same
+/// path since.
+#[derive(PartialEq, Clone, Copy)]
+pub struct Identity {
+    device: u64,
+    inode: u64,
+}
+
+impl Identity {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = symlink_metadata(path)?;
+
+        Ok(Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        })
+    }
+}
+
+/// A filesystem change made by nfm, with what it takes to revert it. The
+/// identity is that of the entry the operation last produced, wherever it
+/// currently is.
+pub enum Operation {
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+        identity: Identity,
+    },
+    Create {
+        path: PathBuf,
+        directory: bool,
+        identity: Identity,
+        modified: SystemTime,
+    },
+    Trash {
+        item: TrashItem,
+        identity: Identity,
+    },
+    Copy {
+        source: PathBuf,
+        target: PathBuf,
+        identity: Identity,
+    },
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+        identity: Identity,
+    },
+    Link {
+        kind: LinkKind,
+        target: PathBuf,
+        path: PathBuf,
+        identity: Identity,
+    },
+}
+
+impl Operation {
+    /// Records that `from` was renamed to `to`.
+    pub fn rename(from: PathBuf, to: PathBuf) -> Result<Self> {
+        let identity = Identity::of(&to)?;
+        Ok(Operation::Rename { from, to, identity })
+    }
+
+    /// Records that `path` was created.
+    pub fn create(path: PathBuf, directory: bool) -> Result<Self> {
+        let identity = Identity::of(&path)?;
+        let modified = symlink_metadata(&path)?.modified()?;
+
+        Ok(Operation::Create {
+            path,
+            directory,
+            identity,
+            modified,
+        })
+    }
+
+    /// Records that `item` was moved to the home trash.
+    pub fn trash(item: TrashItem) -> Result<Self> {
+        let identity = Identity::of(&Trash::home()?.file(&item))?;
+        Ok(Operation::Trash { item, identity })
+    }
+
+    /// Records that `source` was copied to `target`.
+    pub fn copy(source: PathBuf, target: PathBuf) -> Result<Self> {
+        let identity = Identity::of(&target)?;
+        Ok(Operation::Copy {
+            source,
+            target,
+            identity,
+        })
+    }
+
+    /// Records that `from` was moved to `to`.
+    pub fn moved(from: PathBuf, to: PathBuf) -> Result<Self> {
+        let identity = Identity::of(&to)?;
+        Ok(Operation::Move { from, to, identity })
+    }
+
+    /// Records that a link of `kind` to `target` was created at `path`.
+    pub fn link(kind: LinkKind, target: PathBuf, path: PathBuf) -> Result<Self> {
+        let identity = Identity::of(&path)?;
+        Ok(Operation::Link {
+            kind,
+            target,
+            path,
+            identity,
+        })
+    }
+
+    /// Reverts the operation, after checking that what it produced is still
+    /// in place and that what it removed has not been replaced since.
+    fn undo(&mut self) -> Result<()> {
+        match self {
+            Operation::Rename { from, to, identity } => {
+                expect_same(to, *identity)?;
+                expect_absent(from)?;
+                rename(to, from)
+            }
+
+            Operation::Move { from, to, identity } => {
+                expect_same(to, *identity)?;
+                expect_absent(from)?;
+                move_path(to, from)?;
+                *identity = Identity::of(from)?;
+                Ok(())
+            }
+
+            Operation::Create {
+                path,
+                directory,
+                identity,
+                modified,
+            } => {
+                expect_same(path, *identity)?;
+
+                let empty = if *directory {
+                    read_dir(&*path)?.next().is_none()
+                } else {
+                    let metadata = symlink_metadata(&*path)?;
+                    metadata.len() == 0 && metadata.modified()? == *modified
+                };
+
+                if !empty {
+                    return Err(Error::new(
+                        ErrorKind::DirectoryNotEmpty,
+                        format!("{} is no longer empty", name::display(path.as_os_str())),
+                    ));
+                }
+
+                if *directory {
+                    remove_dir(path)
+                } else {
+                    remove_file(path)
+                }
+            }
+
+            Operation::Link {
+                kind,
+                path,
+                identity,
+                ..
+            } => {
+                expect_same(path, *identity)?;
+                link::expect_link(*kind, path)?;
+                remove_file(path)
+            }
+
+            Operation::Trash { item, identity } => {
+                let trash = Trash::home()?;
+
+                if !trash.contains(item) {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        format!(
+                            "{} is no longer in the trash",
+                            name::display(item.original.as_os_str())
+                        ),
+                    ));
+                }
+
+                expect_same(&trash.file(item), *identity)?;
+                trash.restore(item)?;
+                *identity = Identity::of(&item.original)?;
+                Ok(())
+            }
+
+            // The copy goes to the trash rather than away for good, in case
+            // it was changed after being pasted.
+            Operation::Copy {
+                target, identity, ..
+            } => {
+                expect_same(target, *identity)?;
+                Trash::home()?.put(target).map(|_| ())
+            }
+        }
+    }
+
+    /// Performs the operation again after it was undone.
+    fn redo(&mut self) -> Result<()> {
+        match self {
+            Operation::Rename { from, to, identity } => {
+                expect_same(from, *identity)?;
+                expect_absent(to)?;
+                rename(from, to)
+            }
+
+            Operation::Move { from, to, identity } => {
+                expect_same(from, *identity)?;
+                expect_absent(to)?;
+                move_path(from, to)?;
+                *identity = Identity::of(to)?;
+                Ok(())
+            }
+
+            Operation::Create {
+                path, directory, ..
+            } => {
+                expect_absent(path)?;
+
+                if *directory {
+                    create_dir(&*path)?;
+                } else {
+                    File::create_new(&*path)?;
+                }
+
+                *self = Operation::create(path.clone(), *directory)?;
+                Ok(())
+            }
+
+            Operation::Link {
+                kind,
+                target,
+                path,
+                identity,
+            } => {
+                expect_absent(path)?;
+                link::create(*kind, target, path)?;
+                *identity = Identity::of(path)?;
+                Ok(())
+            }
+
+            Operation::Trash { item, identity } => {
+                expect_same(&item.original, *identity)?;
+                let trash = Trash::home()?;
+                *item = trash.put(&item.original)?;
+                *identity = Identity::of(&trash.file(item))?;
+                Ok(())
+            }
+
+            Operation::Copy {
+                source,
+                target,
+                identity,
+            } => {
+                expect_present(source)?;
+                expect_absent(target)?;
+                copy_recursive(source, target)?;
+                *identity = Identity::of(target)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn expect_present(path: &Path) -> Result<()> {
+    symlink_metadata(path).map(|_| ()).map_err(|_| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("{} no longer exists", name::display(path.as_os_str())),
+        )
+    })
+}
+
+/// Checks that `path` still holds the entry with `identity`.
+fn expect_same(path: &Path, identity: Identity) -> Result<()> {
+    expect_present(path)?;
+
+    if Identity::of(path)? != identity {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{} has been replaced since",
+                name::display(path.as_os_str())
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn expect_absent(path: &Path) -> Result<()> {
+    match symlink_metadata(path) {
+        Ok(_) => Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", name::display(path.as_os_str())),
+        )),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Undo and redo history. Operations are grouped into changes, so a bulk
+/// action is undone in one go.
+#[derive(Default)]
+pub struct Journal {
+    undo: Vec<Vec<Operation>>,
+    redo: Vec<Vec<Operation>>,
+}
+
+impl Journal {
+    /// Records a change made by the user, which forgets anything undone.
+    pub fn record(&mut self, change: Vec<Operation>) {
+        if change.is_empty() {
+            return;
+        }
+
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// Reverts the last change. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self) -> Result<bool> {
+        let Some(mut change) = self.undo.pop() else {
+            return Ok(false);
+        };
+
+        match Self::replay(&mut change, true) {
+            Ok(()) => self.redo.push(change),
+            Err(error) => {
+                self.undo.push(change);
+                return Err(error);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Performs the last undone change again. Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo(&mut self) -> Result<bool> {
+        let Some(mut change) = self.redo.pop() else {
+            return Ok(false);
+        };
+
+        match Self::replay(&mut change, false) {
+            Ok(()) => self.undo.push(change),
+            Err(error) => {
+                self.redo.push(change);
+                return Err(error);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Undoes or redoes every operation of `change`. If one of them cannot
+    /// be replayed, the ones already replayed are reverted so the change is
+    /// either applied as a whole or not at all.
+    fn replay(change: &mut [Operation], undo: bool) -> Result<()> {
+        let order = if undo {
+            (0..change.len()).rev().collect::<Vec<_>>()
+        } else {
+            (0..change.len()).collect()
+        };
+
+        for (done, &index) in order.iter().enumerate() {
+            let result = if undo {
+                change[index].undo()
+            } else {
+                change[index].redo()
+            };
+
+            if let Err(error) = result {
+                for &index in order[..done].iter().rev() {
+                    let _ = if undo {
+                        change[index].redo()
+                    } else {
+                        change[index].undo()
+                    };
+                }
+
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, read_to_string},
+        process,
+    };
+
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("nfm-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn renamed(directory: &Path, from: &str, to: &str) -> Operation {
+        let (from, to) = (directory.join(from), directory.join(to));
+        rename(&from, &to).unwrap();
+        Operation::rename(from, to).unwrap()
+    }
+
+    /// Puts a new entry at `path` without reusing the inode of the old one.
+    fn replace(path: &Path) {
+        let replacement = path.with_extension("replacement");
+        fs::write(&replacement, "replacement").unwrap();
+        rename(&replacement, path).unwrap();
+    }
+
+    #[test]
+    fn renames_are_undone_and_redone() {
+        let directory = scratch("journal-rename");
+        fs::write(directory.join("a"), "a").unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![renamed(&directory, "a", "b")]);
+
+        assert!(journal.undo().unwrap());
+        assert_eq!(read_to_string(directory.join("a")).unwrap(), "a");
+        assert!(!journal.undo().unwrap());
+
+        assert!(journal.redo().unwrap());
+        assert_eq!(read_to_string(directory.join("b")).unwrap(), "a");
+        assert!(!journal.redo().unwrap());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn replaced_entries_are_left_alone() {
+        let directory = scratch("journal-replaced");
+        fs::write(directory.join("a"), "a").unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![renamed(&directory, "a", "b")]);
+        replace(&directory.join("b"));
+
+        assert_eq!(journal.undo().unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(read_to_string(directory.join("b")).unwrap(), "replacement");
+        assert!(symlink_metadata(directory.join("a")).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn taken_names_are_not_overwritten() {
+        let directory = scratch("journal-taken");
+        fs::write(directory.join("a"), "a").unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![renamed(&directory, "a", "b")]);
+        fs::write(directory.join("a"), "new a").unwrap();
+
+        assert_eq!(journal.undo().unwrap_err().kind(), ErrorKind::AlreadyExists);
+        assert_eq!(read_to_string(directory.join("a")).unwrap(), "new a");
+
+        // The change stays in the history and goes through once the way is
+        // clear again.
+        fs::remove_file(directory.join("a")).unwrap();
+        assert!(journal.undo().unwrap());
+        assert_eq!(read_to_string(directory.join("a")).unwrap(), "a");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn changes_are_undone_as_a_whole_or_not_at_all() {
+        let directory = scratch("journal-whole");
+        fs::write(directory.join("a"), "a").unwrap();
+        fs::write(directory.join("c"), "c").unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![
+            renamed(&directory, "a", "b"),
+            renamed(&directory, "c", "d"),
+        ]);
+        replace(&directory.join("b"));
+
+        // The second rename is undone first, then put back when the first
+        // one turns out to have been replaced.
+        assert!(journal.undo().is_err());
+        assert_eq!(read_to_string(directory.join("d")).unwrap(), "c");
+        assert!(symlink_metadata(directory.join("c")).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn only_untouched_creations_are_undone() {
+        let directory = scratch("journal-create");
+        let path = directory.join("new");
+
+        File::create_new(&path).unwrap();
+        let mut journal = Journal::default();
+        journal.record(vec![Operation::create(path.clone(), false).unwrap()]);
+
+        fs::write(&path, "written").unwrap();
+        assert_eq!(
+            journal.undo().unwrap_err().kind(),
+            ErrorKind::DirectoryNotEmpty
+        );
+        assert_eq!(read_to_string(&path).unwrap(), "written");
+
+        fs::remove_file(&path).unwrap();
+        File::create_new(&path).unwrap();
+        let mut journal = Journal::default();
+        journal.record(vec![Operation::create(path.clone(), false).unwrap()]);
+
+        assert!(journal.undo().unwrap());
+        assert!(symlink_metadata(&path).is_err());
+        assert!(journal.redo().unwrap());
+        assert_eq!(symlink_metadata(&path).unwrap().len(), 0);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A single key press together with its modifiers, written as `a`, `C-a`,
+/// `A-Enter` or `C-S-Up` in the configuration.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already part of the character itself, so `A` and `S-a`
+        // would otherwise never compare equal to what the terminal reports.
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<&KeyEvent> for KeyChord {
+    fn from(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let invalid = || Error::new(ErrorKind::InvalidData, format!("invalid key `{}`", source));
+
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = source;
+
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            modifiers |= match &rest[..1] {
+                "C" => KeyModifiers::CONTROL,
+                "A" | "M" => KeyModifiers::ALT,
+                "S" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+
+            rest = &rest[2..];
+        }
+
+        let code = match rest {
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Backspace" => KeyCode::Backspace,
+            "Tab" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Space" => KeyCode::Char(' '),
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Delete" => KeyCode::Delete,
+            "Insert" => KeyCode::Insert,
+            _ if rest.len() > 1 && rest.starts_with('F') => {
+                KeyCode::F(rest[1..].parse().map_err(|_| invalid())?)
+            }
+            _ => {
+                let mut characters = rest.chars();
+
+                match (characters.next(), characters.next()) {
+                    (Some(character), None) => KeyCode::Char(character),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", prefix)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(character) => write!(f, "{}", character),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(source: &str) -> KeyChord {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn chords_are_written_back_as_they_are_parsed() {
+        for source in [
+            "a", "A", "?", "C-a", "A-Enter", "C-S-Up", "Space", "F12", "BackTab",
+        ] {
+            assert_eq!(chord(source).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn modifiers_are_read_in_any_order() {
+        let up = chord("S-C-Up");
+        assert!(up.code == KeyCode::Up);
+        assert!(up.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(chord("M-x").to_string(), "A-x");
+    }
+
+    #[test]
+    fn shifted_characters_match_what_the_terminal_reports() {
+        let event = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT);
+        assert!(KeyChord::from(&event) == chord("A"));
+
+        let event = KeyEvent::new(
+            KeyCode::Char('R'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert!(KeyChord::from(&event) == chord("C-R"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for source in ["", "ab", "Q-a", "C-", "Fx", "Return"] {
+            assert!(source.parse::<KeyChord>().is_err(), "{:?}", source);
+        }
+    }
+}
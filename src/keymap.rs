@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::{action::Action, key::KeyChord, mode::Mode, transfer::ConflictPolicy};
+
+/// A sequence of chords bound to an action, such as `g g` or `C-x C-s`.
+pub type Sequence = Vec<KeyChord>;
+
+pub type Bindings = Vec<(Sequence, Action)>;
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// hjkl navigation and vim-like sequences such as `gg` and `dd`.
+    #[default]
+    Vim,
+    /// The single-key bindings nfm had before the vim-like ones, still read
+    /// from configurations that name them `default`.
+    #[serde(alias = "default")]
+    Classic,
+}
+
+pub enum Resolution {
+    Bound(Action),
+    Pending,
+    Unbound,
+}
+
+pub struct Keymap {
+    bindings: HashMap<Mode, Bindings>,
+    pending: Sequence,
+}
+
+impl Keymap {
+    pub fn new(preset: Preset) -> Self {
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+            pending: Vec::new(),
+        };
+
+        let classic_normal: &[(&str, Action)] = &[
+            ("Esc", Action::Close),
+            ("Up", Action::MoveUp),
+            ("Down", Action::MoveDown),
+            ("C-Up", Action::ScrollUp),
+            ("C-Down", Action::ScrollDown),
+            ("Enter", Action::Open),
+            ("Backspace", Action::Back),
+            ("Home", Action::Home),
+            ("End", Action::End),
+            ("h", Action::ToggleHidden),
+            ("P", Action::TogglePreview),
+            ("C", Action::ToggleColumns),
+            ("L", Action::ToggleDetails),
+            ("@", Action::ToggleFollowLinks),
+            ("J", Action::JumpToTarget),
+            ("s", Action::CycleSort),
+            ("S", Action::ReverseSort),
+            ("C-s", Action::ToggleDirectoriesFirst),
+            ("V", Action::ToggleDual),
+            ("o", Action::SwitchPane),
+            ("X", Action::SwapPanes),
+            ("=", Action::SyncPane),
+            ("t", Action::NewTab),
+            ("w", Action::CloseTab),
+            ("Tab", Action::NextTab),
+            ("BackTab", Action::PreviousTab),
+            ("r", Action::Rename),
+            ("R", Action::BulkRename),
+            ("d", Action::Remove),
+            ("D", Action::Delete),
+            ("T", Action::ShowTrash),
+            ("a", Action::Add),
+            ("l", Action::Link),
+            ("c", Action::EditPermissions),
+            ("/", Action::Search),
+            ("f", Action::Find),
+            ("F", Action::Grep),
+            ("?", Action::ToggleHelp),
+            ("Space", Action::ToggleMark),
+            ("A", Action::MarkAll),
+            ("I", Action::InvertMarks),
+            ("*", Action::MarkGlob),
+            ("y", Action::Copy),
+            ("x", Action::Cut),
+            ("p", Action::Paste),
+            ("u", Action::Undo),
+            ("U", Action::Redo),
+        ];
+
+        let vim_normal: &[(&str, Action)] = &[
+            ("q", Action::Close),
+            ("Esc", Action::Close),
+            ("k", Action::MoveUp),
+            ("Up", Action::MoveUp),
+            ("j", Action::MoveDown),
+            ("Down", Action::MoveDown),
+            ("C-y", Action::ScrollUp),
+            ("C-e", Action::ScrollDown),
+            ("l", Action::Open),
+            ("Enter", Action::Open),
+            ("h", Action::Back),
+            ("Backspace", Action::Back),
+            ("gg", Action::Home),
+            ("G", Action::End),
+            ("zh", Action::ToggleHidden),
+            ("zp", Action::TogglePreview),
+            ("zc", Action::ToggleColumns),
+            ("zl", Action::ToggleDetails),
+            ("z@", Action::ToggleFollowLinks),
+            ("gl", Action::JumpToTarget),
+            ("zs", Action::CycleSort),
+            ("zS", Action::ReverseSort),
+            ("zd", Action::ToggleDirectoriesFirst),
+            ("zv", Action::ToggleDual),
+            ("o", Action::SwitchPane),
+            ("X", Action::SwapPanes),
+            ("=", Action::SyncPane),
+            ("tn", Action::NewTab),
+            ("tc", Action::CloseTab),
+            ("gt", Action::NextTab),
+            ("gT", Action::PreviousTab),
+            ("cw", Action::Rename),
+            ("R", Action::BulkRename),
+            ("dd", Action::Remove),
+            ("dD", Action::Delete),
+            ("T", Action::ShowTrash),
+            ("a", Action::Add),
+            ("gL", Action::Link),
+            ("cm", Action::EditPermissions),
+            ("/", Action::Search),
+            ("f", Action::Find),
+            ("F", Action::Grep),
+            ("?", Action::ToggleHelp),
+            ("Space", Action::ToggleMark),
+            ("A", Action::MarkAll),
+            ("I", Action::InvertMarks),
+            ("*", Action::MarkGlob),
+            ("yy", Action::Copy),
+            ("x", Action::Cut),
+            ("p", Action::Paste),
+            ("u", Action::Undo),
+            ("C-r", Action::Redo),
+        ];
+
+        let normal = match preset {
+            Preset::Vim => vim_normal,
+            Preset::Classic => classic_normal,
+        };
+
+        // The finder and content search share their list and prompt.
+        let results: &[(&str, Action)] = &[
+            ("Esc", Action::Close),
+            ("Up", Action::MoveUp),
+            ("C-p", Action::MoveUp),
+            ("Down", Action::MoveDown),
+            ("C-n", Action::MoveDown),
+            ("Home", Action::Home),
+            ("End", Action::End),
+            ("Enter", Action::Open),
+            ("Tab", Action::CycleMatcher),
+            ("C-t", Action::ToggleSmartCase),
+        ];
+
+        let modes: &[(Mode, &[(&str, Action)])] = &[
+            (Mode::Normal, normal),
+            (Mode::Rename, &[("Esc", Action::Close)]),
+            (
+                Mode::BulkRename,
+                &[("Esc", Action::Close), ("Enter", Action::BulkRename)],
+            ),
+            (
+                Mode::Remove,
+                &[("Esc", Action::Close), ("Enter", Action::Remove)],
+            ),
+            (
+                Mode::Delete,
+                &[("Esc", Action::Close), ("Enter", Action::Delete)],
+            ),
+            (
+                Mode::Trash,
+                &[
+                    ("Esc", Action::Close),
+                    ("T", Action::Close),
+                    ("Up", Action::MoveUp),
+                    ("k", Action::MoveUp),
+                    ("Down", Action::MoveDown),
+                    ("j", Action::MoveDown),
+                    ("Home", Action::Home),
+                    ("End", Action::End),
+                    ("Enter", Action::Restore),
+                    ("r", Action::Restore),
+                    ("d", Action::Purge),
+                ],
+            ),
+            (
+                Mode::Purge,
+                &[("Esc", Action::Close), ("Enter", Action::Purge)],
+            ),
+            (
+                Mode::Add,
+                &[
+                    ("Esc", Action::Close),
+                    ("Enter", Action::Add),
+                    ("Tab", Action::Complete),
+                ],
+            ),
+            (
+                Mode::Link,
+                &[
+                    ("Esc", Action::Close),
+                    ("Enter", Action::Link),
+                    ("Tab", Action::Complete),
+                    ("C-t", Action::CycleLinkKind),
+                ],
+            ),
+            (
+                Mode::Permissions,
+                &[("Esc", Action::Close), ("Enter", Action::EditPermissions)],
+            ),
+            (
+                Mode::Search,
+                &[
+                    ("Esc", Action::Close),
+                    ("Tab", Action::CycleMatcher),
+                    ("C-t", Action::ToggleSmartCase),
+                ],
+            ),
+            (Mode::Find, results),
+            (Mode::Grep, results),
+            (
+                Mode::Mark,
+                &[("Esc", Action::Close), ("Enter", Action::MarkGlob)],
+            ),
+            (
+                Mode::Conflict,
+                &[
+                    ("Esc", Action::Close),
+                    ("s", Action::Resolve(ConflictPolicy::Skip)),
+                    ("o", Action::Resolve(ConflictPolicy::Overwrite)),
+                    ("r", Action::Resolve(ConflictPolicy::KeepBoth)),
+                    ("S", Action::ResolveAll(ConflictPolicy::Skip)),
+                    ("O", Action::ResolveAll(ConflictPolicy::Overwrite)),
+                    ("R", Action::ResolveAll(ConflictPolicy::KeepBoth)),
+                ],
+            ),
+            (Mode::Help, &[("Esc", Action::Close), ("?", Action::Close)]),
+            (
+                Mode::Message,
+                &[("Esc", Action::Close), ("Enter", Action::Close)],
+            ),
+        ];
+
+        for (mode, bindings) in modes {
+            for (sequence, action) in bindings.iter() {
+                keymap.bind(
+                    mode.clone(),
+                    parse_sequence(sequence).expect("built-in bindings are valid"),
+                    action.clone(),
+                );
+            }
+        }
+
+        keymap
+    }
+
+    /// Binds `sequence` to `action` in `mode`, replacing any previous binding
+    /// of the same sequence.
+    pub fn bind(&mut self, mode: Mode, sequence: Sequence, action: Action) {
+        let bindings = self.bindings.entry(mode).or_default();
+        bindings.retain(|(bound, _)| *bound != sequence);
+        bindings.push((sequence, action));
+    }
+
+    /// Feeds `chord` into the pending sequence. A sequence that is bound
+    /// fires right away, even if it is also the start of a longer one.
+    pub fn resolve(&mut self, mode: &Mode, chord: KeyChord) -> Resolution {
+        let Some(bindings) = self.bindings.get(mode) else {
+            self.pending.clear();
+            return Resolution::Unbound;
+        };
+
+        self.pending.push(chord);
+
+        if let Some((_, action)) = bindings.iter().find(|(bound, _)| *bound == self.pending) {
+            self.pending.clear();
+            return Resolution::Bound(action.clone());
+        }
+
+        if bindings
+            .iter()
+            .any(|(bound, _)| bound.starts_with(&self.pending))
+        {
+            return Resolution::Pending;
+        }
+
+        // A broken sequence is dropped, but its last chord may still start
+        // or be a binding of its own.
+        let was_pending = self.pending.len() > 1;
+        self.pending.clear();
+
+        if was_pending {
+            self.resolve(mode, chord)
+        } else {
+            Resolution::Unbound
+        }
+    }
+
+    /// The sequences bound to `action` in `mode`, in the order they were bound.
+    pub fn sequences(&self, mode: &Mode, action: &Action) -> Vec<&Sequence> {
+        self.bindings
+            .get(mode)
+            .map(|bindings| {
+                bindings
+                    .iter()
+                    .filter(|(_, bound)| bound == action)
+                    .map(|(sequence, _)| sequence)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Parses a sequence written either as space separated chords (`C-x C-s`),
+/// a single chord (`Enter`, `C-r`) or a run of plain characters (`gg`).
+pub fn parse_sequence(source: &str) -> std::io::Result<Sequence> {
+    if source.contains(char::is_whitespace) {
+        return source.split_whitespace().map(str::parse).collect();
+    }
+
+    match source.parse() {
+        Ok(chord) => Ok(vec![chord]),
+        Err(_) if source.chars().count() > 1 && !source.contains('-') => source
+            .chars()
+            .map(|character| character.to_string().parse())
+            .collect(),
+        Err(error) => Err(error),
+    }
+}
+
+/// Formats `sequence` the way it is written in the configuration.
+pub fn format_sequence(sequence: &Sequence) -> String {
+    let plain = sequence
+        .iter()
+        .all(|chord| chord.to_string().chars().count() == 1);
+
+    sequence
+        .iter()
+        .map(KeyChord::to_string)
+        .collect::<Vec<_>>()
+        .join(if plain { "" } else { " " })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(keymap: &mut Keymap, keys: &str) -> Resolution {
+        let mut resolution = Resolution::Unbound;
+
+        for chord in parse_sequence(keys).unwrap() {
+            resolution = keymap.resolve(&Mode::Normal, chord);
+        }
+
+        resolution
+    }
+
+    #[test]
+    fn sequences_are_parsed_from_chords_or_runs_of_characters() {
+        assert_eq!(
+            format_sequence(&parse_sequence("C-x C-s").unwrap()),
+            "C-x C-s"
+        );
+        assert_eq!(parse_sequence("gg").unwrap().len(), 2);
+        assert_eq!(parse_sequence("Enter").unwrap().len(), 1);
+        assert_eq!(format_sequence(&parse_sequence("g T").unwrap()), "gT");
+        assert!(parse_sequence("C-x Q-s").is_err());
+        assert!(parse_sequence("Z-q").is_err());
+    }
+
+    #[test]
+    fn sequences_wait_for_their_last_chord() {
+        let mut keymap = Keymap::new(Preset::Vim);
+
+        let mut keys = parse_sequence("gg").unwrap().into_iter();
+        assert!(matches!(
+            keymap.resolve(&Mode::Normal, keys.next().unwrap()),
+            Resolution::Pending
+        ));
+        assert!(matches!(
+            keymap.resolve(&Mode::Normal, keys.next().unwrap()),
+            Resolution::Bound(Action::Home)
+        ));
+        assert!(matches!(
+            press(&mut keymap, "dD"),
+            Resolution::Bound(Action::Delete)
+        ));
+    }
+
+    #[test]
+    fn broken_sequences_retry_their_last_chord() {
+        let mut keymap = Keymap::new(Preset::Vim);
+
+        assert!(matches!(
+            press(&mut keymap, "gj"),
+            Resolution::Bound(Action::MoveDown)
+        ));
+        assert!(matches!(press(&mut keymap, "gQ"), Resolution::Unbound));
+        assert!(matches!(
+            press(&mut keymap, "G"),
+            Resolution::Bound(Action::End)
+        ));
+    }
+
+    #[test]
+    fn bound_prefixes_fire_before_longer_sequences() {
+        let mut keymap = Keymap::new(Preset::Vim);
+        keymap.bind(Mode::Normal, parse_sequence("zzz").unwrap(), Action::Redraw);
+        keymap.bind(Mode::Normal, parse_sequence("z").unwrap(), Action::Search);
+
+        assert!(matches!(
+            press(&mut keymap, "z"),
+            Resolution::Bound(Action::Search)
+        ));
+    }
+
+    #[test]
+    fn binding_a_sequence_again_replaces_it() {
+        let mut keymap = Keymap::new(Preset::Classic);
+        keymap.bind(Mode::Normal, parse_sequence("d").unwrap(), Action::Delete);
+
+        assert!(matches!(
+            press(&mut keymap, "d"),
+            Resolution::Bound(Action::Delete)
+        ));
+        assert_eq!(keymap.sequences(&Mode::Normal, &Action::Delete).len(), 2);
+        assert!(keymap.sequences(&Mode::Normal, &Action::Remove).is_empty());
+    }
+
+    #[test]
+    fn the_old_preset_name_still_picks_the_classic_bindings() {
+        let keys: crate::config::Keys = toml::from_str("preset = \"default\"").unwrap();
+        assert!(matches!(keys.preset, Preset::Classic));
+
+        let keys: crate::config::Keys = toml::from_str("").unwrap();
+        assert!(matches!(keys.preset, Preset::Vim));
+    }
+}
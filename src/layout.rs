@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// The listing alone.
+    #[default]
+    Single,
+    /// The listing with a preview of the selection on the right.
+    Preview,
+    /// Miller columns: the parent directory, the listing and a preview of
+    /// the selection, which for a directory is its own listing.
+    Columns,
+    /// Two listings side by side, each in its own box.
+    Dual,
+}
+
+/// Where each pane goes, as a starting column and a width. Panes that do not
+/// fit are left out.
+pub struct Panes {
+    pub parent: Option<(u16, u16)>,
+    pub list: (u16, u16),
+    pub preview: Option<(u16, u16)>,
+    /// The listing that is not in use in the dual layout.
+    pub other: Option<(u16, u16)>,
+}
+
+impl Layout {
+    pub fn panes(self, width: u16) -> Panes {
+        match self {
+            Layout::Preview if width >= 40 => Panes {
+                parent: None,
+                list: (0, width / 2),
+                preview: Some((width / 2, width - width / 2)),
+                other: None,
+            },
+
+            Layout::Columns if width >= 60 => {
+                let parent = width / 5;
+                let list = width * 2 / 5;
+
+                Panes {
+                    parent: Some((0, parent)),
+                    list: (parent + 1, list),
+                    preview: Some((parent + 1 + list, width - parent - 1 - list)),
+                    other: None,
+                }
+            }
+
+            // Each listing leaves a column on both sides for its box.
+            Layout::Dual if width >= 40 => Panes {
+                parent: None,
+                list: (1, width / 2 - 2),
+                preview: None,
+                other: Some((width / 2 + 1, width - width / 2 - 2)),
+            },
+
+            _ => Panes {
+                parent: None,
+                list: (0, width),
+                preview: None,
+                other: None,
+            },
+        }
+    }
+}
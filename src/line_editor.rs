@@ -0,0 +1,410 @@
+use std::{mem, path::Path};
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    completion::{self, Menu},
+    history::History,
+    key::KeyChord,
+    text,
+};
+
+/// The text of a prompt and the cursor in it, edited with readline keys:
+/// `C-a`/`C-e` and `Home`/`End` go to either end, `A-b`/`A-f` and
+/// `C-Left`/`C-Right` move by words, `C-w`, `A-d`, `C-u` and `C-k` kill
+/// text that `C-y` yanks back, and `Up`/`Down` and `A-p`/`A-n` browse the
+/// history of the prompt. Path prompts also complete what is before the
+/// cursor.
+pub struct LineEditor {
+    /// Names the history of the prompt.
+    pub name: &'static str,
+    text: String,
+    /// Byte index of the cursor, always on a grapheme cluster boundary.
+    cursor: usize,
+    /// What the last kill removed.
+    killed: String,
+    /// The history entry in view and the text typed before browsing started.
+    browsing: Option<(usize, String)>,
+    /// The candidates of the last completion, while it leaves a choice.
+    menu: Option<Menu>,
+}
+
+impl LineEditor {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            text: String::new(),
+            cursor: 0,
+            killed: String::new(),
+            browsing: None,
+            menu: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replaces the text, with the cursor at its end.
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+        self.browsing = None;
+        self.menu = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    /// Takes the text out, leaving the prompt empty.
+    pub fn take(&mut self) -> String {
+        let text = mem::take(&mut self.text);
+        self.clear();
+        text
+    }
+
+    pub fn menu(&self) -> Option<&Menu> {
+        self.menu.as_ref()
+    }
+
+    /// Hides the completion menu, returning whether it was shown.
+    pub fn close_menu(&mut self) -> bool {
+        self.menu.take().is_some()
+    }
+
+    /// Columns between the start of the text and the cursor.
+    pub fn cursor_column(&self) -> usize {
+        text::width(&self.text[..self.cursor])
+    }
+
+    /// Inserts `pasted` at the cursor, without the line breaks a prompt
+    /// cannot hold.
+    pub fn paste(&mut self, pasted: &str) {
+        let pasted = pasted
+            .chars()
+            .filter(|character| !character.is_control())
+            .collect::<String>();
+
+        self.menu = None;
+        self.insert(&pasted);
+    }
+
+    /// Completes the path before the cursor, relative to `directory`, as far
+    /// as its candidates agree, and offers them in a menu when more than one
+    /// is left. While the menu is shown, completing again puts the next
+    /// candidate in the prompt instead.
+    pub fn complete(&mut self, directory: &Path) {
+        if self.menu.is_some() {
+            self.pick(true);
+            return;
+        }
+
+        let candidates = completion::candidates(&self.text[..self.cursor], directory);
+        let Some(first) = candidates.first() else {
+            return;
+        };
+
+        let common = candidates[1..]
+            .iter()
+            .fold(first.len(), |common, candidate| {
+                first[..common]
+                    .grapheme_indices(true)
+                    .zip(candidate.graphemes(true))
+                    .find(|((_, a), b)| a != b)
+                    .map_or(common.min(candidate.len()), |((index, _), _)| index)
+            });
+
+        if common > self.cursor {
+            self.replace_before_cursor(&first[..common]);
+        }
+
+        if candidates.len() > 1 {
+            self.menu = Some(Menu {
+                candidates,
+                selection: None,
+            });
+        }
+    }
+
+    /// Carries out the editing command `chord` stands for, with `history`
+    /// to browse. Returns whether it was one.
+    pub fn edit(&mut self, chord: KeyChord, history: &History) -> bool {
+        let control = chord.modifiers == KeyModifiers::CONTROL;
+        let alt = chord.modifiers == KeyModifiers::ALT;
+
+        if chord.code == KeyCode::BackTab && self.menu.is_some() {
+            self.pick(false);
+            return true;
+        }
+
+        self.menu = None;
+
+        match chord.code {
+            KeyCode::Char(character) if chord.modifiers.is_empty() => {
+                let mut buffer = [0; 4];
+                self.insert(character.encode_utf8(&mut buffer));
+            }
+
+            KeyCode::Backspace => self.delete(self.previous(), self.cursor),
+            KeyCode::Char('h') if control => self.delete(self.previous(), self.cursor),
+            KeyCode::Delete => self.delete(self.cursor, self.next()),
+            KeyCode::Char('d') if control => self.delete(self.cursor, self.next()),
+
+            KeyCode::Left if control => self.cursor = self.previous_word(),
+            KeyCode::Right if control => self.cursor = self.next_word(),
+            KeyCode::Char('b') if alt => self.cursor = self.previous_word(),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(),
+            KeyCode::Left => self.cursor = self.previous(),
+            KeyCode::Right => self.cursor = self.next(),
+            KeyCode::Char('b') if control => self.cursor = self.previous(),
+            KeyCode::Char('f') if control => self.cursor = self.next(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Char('e') if control => self.cursor = self.text.len(),
+
+            KeyCode::Char('w') if control => self.kill(self.previous_word(), self.cursor),
+            KeyCode::Char('d') if alt => self.kill(self.cursor, self.next_word()),
+            KeyCode::Char('u') if control => self.kill(0, self.cursor),
+            KeyCode::Char('k') if control => self.kill(self.cursor, self.text.len()),
+            KeyCode::Char('y') if control => self.insert(&self.killed.clone()),
+
+            KeyCode::Up => self.browse_back(history.entries(self.name)),
+            KeyCode::Char('p') if control || alt => self.browse_back(history.entries(self.name)),
+            KeyCode::Down => self.browse_forward(history.entries(self.name)),
+            KeyCode::Char('n') if control || alt => self.browse_forward(history.entries(self.name)),
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Puts the next or previous candidate of the menu in the prompt.
+    fn pick(&mut self, forward: bool) {
+        let Some(menu) = &mut self.menu else {
+            return;
+        };
+
+        let count = menu.candidates.len();
+        let selection = match menu.selection {
+            Some(selection) if forward => (selection + 1) % count,
+            Some(selection) => (selection + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        };
+
+        menu.selection = Some(selection);
+
+        let candidate = menu.candidates[selection].clone();
+        self.replace_before_cursor(&candidate);
+    }
+
+    fn replace_before_cursor(&mut self, replacement: &str) {
+        self.text.replace_range(..self.cursor, replacement);
+        self.cursor = replacement.len();
+        self.browsing = None;
+    }
+
+    fn insert(&mut self, inserted: &str) {
+        self.text.insert_str(self.cursor, inserted);
+        self.cursor += inserted.len();
+
+        // What was inserted may have merged with the text around it into a
+        // single grapheme cluster, which the cursor must not end up inside.
+        let end = text::next(&self.text, self.previous());
+
+        if end > self.cursor {
+            self.cursor = end;
+        }
+    }
+
+    fn delete(&mut self, start: usize, end: usize) {
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.killed = self.text[start..end].to_string();
+            self.delete(start, end);
+        }
+    }
+
+    fn previous(&self) -> usize {
+        text::previous(&self.text, self.cursor)
+    }
+
+    fn next(&self) -> usize {
+        text::next(&self.text, self.cursor)
+    }
+
+    /// Start of the word before the cursor, words being runs of letters,
+    /// digits and underscores.
+    fn previous_word(&self) -> usize {
+        let mut graphemes = self.text[..self.cursor]
+            .grapheme_indices(true)
+            .rev()
+            .peekable();
+
+        while graphemes
+            .next_if(|(_, grapheme)| !is_word(grapheme))
+            .is_some()
+        {}
+
+        let mut start = graphemes.peek().map_or(0, |(index, _)| *index);
+
+        while let Some((index, _)) = graphemes.next_if(|(_, grapheme)| is_word(grapheme)) {
+            start = index;
+        }
+
+        start
+    }
+
+    /// End of the word after the cursor.
+    fn next_word(&self) -> usize {
+        let mut graphemes = self.text[self.cursor..].grapheme_indices(true).peekable();
+
+        while graphemes
+            .next_if(|(_, grapheme)| !is_word(grapheme))
+            .is_some()
+        {}
+        while graphemes
+            .next_if(|(_, grapheme)| is_word(grapheme))
+            .is_some()
+        {}
+
+        self.cursor
+            + graphemes
+                .peek()
+                .map_or(self.text.len() - self.cursor, |(index, _)| *index)
+    }
+
+    fn browse_back(&mut self, entries: &[String]) {
+        let index = match &self.browsing {
+            Some((0, _)) => return,
+            Some((index, _)) => index - 1,
+            None if entries.is_empty() => return,
+            None => entries.len() - 1,
+        };
+
+        let draft = match self.browsing.take() {
+            Some((_, draft)) => draft,
+            None => self.text.clone(),
+        };
+
+        self.set(entries[index].clone());
+        self.browsing = Some((index, draft));
+    }
+
+    fn browse_forward(&mut self, entries: &[String]) {
+        let Some((index, draft)) = self.browsing.take() else {
+            return;
+        };
+
+        match entries.get(index + 1) {
+            Some(entry) => {
+                self.set(entry.clone());
+                self.browsing = Some((index + 1, draft));
+            }
+            None => self.set(draft),
+        }
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|character| character.is_alphanumeric() || character == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut LineEditor, keys: &str) {
+        let history = History::default();
+
+        for key in keys.split_whitespace() {
+            assert!(editor.edit(key.parse().unwrap(), &history), "{}", key);
+        }
+    }
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new("test");
+        editor.set(text.to_string());
+        editor
+    }
+
+    #[test]
+    fn the_cursor_moves_over_whole_grapheme_clusters() {
+        let mut editor = editor("ae\u{301}👩‍💻z");
+
+        press(&mut editor, "Left Left");
+        assert_eq!(editor.cursor, "ae\u{301}".len());
+        assert_eq!(editor.cursor_column(), 2);
+
+        press(&mut editor, "Backspace");
+        assert_eq!(editor.text(), "a👩‍💻z");
+        press(&mut editor, "Delete");
+        assert_eq!(editor.text(), "az");
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let mut editor = editor("日本語");
+        assert_eq!(editor.cursor_column(), 6);
+
+        press(&mut editor, "Left");
+        assert_eq!(editor.cursor_column(), 4);
+    }
+
+    #[test]
+    fn combining_marks_join_the_character_before_the_cursor() {
+        let mut editor = editor("e");
+        editor.paste("\u{301}");
+        press(&mut editor, "Left");
+
+        assert_eq!(editor.cursor, 0);
+        assert_eq!(editor.cursor_column(), 0);
+    }
+
+    #[test]
+    fn kills_can_be_yanked_back() {
+        let mut editor = editor("cp some_file.txt dest");
+
+        press(&mut editor, "C-w");
+        assert_eq!(editor.text(), "cp some_file.txt ");
+        press(&mut editor, "A-b C-k");
+        assert_eq!(editor.text(), "cp some_file.");
+        press(&mut editor, "C-a C-y");
+        assert_eq!(editor.text(), "txt cp some_file.");
+        assert_eq!(editor.cursor, "txt ".len());
+
+        press(&mut editor, "A-d");
+        assert_eq!(editor.text(), "txt  some_file.");
+        press(&mut editor, "End C-y");
+        assert_eq!(editor.text(), "txt  some_file.cp");
+        press(&mut editor, "C-b C-u");
+        assert_eq!(editor.text(), "p");
+    }
+
+    #[test]
+    fn words_are_runs_of_letters_digits_and_underscores() {
+        let mut editor = editor("über/naïve_2 x");
+
+        press(&mut editor, "Home A-f");
+        assert_eq!(editor.cursor, "über".len());
+        press(&mut editor, "C-Right");
+        assert_eq!(editor.cursor, "über/naïve_2".len());
+        press(&mut editor, "C-Left C-Left");
+        assert_eq!(editor.cursor, 0);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    fs::{canonicalize, hard_link, symlink_metadata},
+    io::{Error, ErrorKind, Result},
+    os::unix::fs::symlink,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::name;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    /// A symbolic link holding the absolute path of its target.
+    Absolute,
+    /// A symbolic link holding the path of its target relative to the
+    /// directory it is in, which survives moving both together.
+    Relative,
+    Hard,
+}
+
+impl LinkKind {
+    pub fn next(self) -> Self {
+        match self {
+            LinkKind::Absolute => LinkKind::Relative,
+            LinkKind::Relative => LinkKind::Hard,
+            LinkKind::Hard => LinkKind::Absolute,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LinkKind::Absolute => "absolute symbolic link",
+            LinkKind::Relative => "relative symbolic link",
+            LinkKind::Hard => "hard link",
+        }
+    }
+}
+
+/// Creates a link of `kind` at `path` to `target`, both absolute.
+pub fn create(kind: LinkKind, target: &Path, path: &Path) -> Result<()> {
+    match kind {
+        LinkKind::Absolute => symlink(target, path),
+        LinkKind::Relative => {
+            let directory = canonicalize(path.parent().unwrap_or(path))?;
+            symlink(relative(&directory, &resolve_parent(target)?), path)
+        }
+        LinkKind::Hard => hard_link(target, path),
+    }
+}
+
+/// Checks that `path` is still a link of `kind`, before removing it.
+pub fn expect_link(kind: LinkKind, path: &Path) -> Result<()> {
+    if symlink_metadata(path)?.file_type().is_symlink() == (kind != LinkKind::Hard) {
+        return Ok(());
+    }
+
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "{} is no longer the link that was created",
+            name::display(path.as_os_str())
+        ),
+    ))
+}
+
+/// `path` with `.`, `..` and links resolved in the directories leading to it,
+/// but not in its last component, which may be a link on purpose.
+fn resolve_parent(path: &Path) -> Result<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => Ok(canonicalize(parent)?.join(name)),
+        _ => canonicalize(path),
+    }
+}
+
+/// The path leading from the directory `from` to `to`, both absolute and
+/// resolved, going up with `..` as far as needed.
+fn relative(from: &Path, to: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    from[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(to[common..].iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, read_link},
+        process,
+    };
+
+    use super::*;
+
+    #[test]
+    fn relative_paths_go_up_to_the_common_directory() {
+        let relative = |from: &str, to: &str| relative(Path::new(from), Path::new(to));
+
+        assert_eq!(relative("/a/b", "/a/b/c"), Path::new("c"));
+        assert_eq!(relative("/a/b", "/a/c/d"), Path::new("../c/d"));
+        assert_eq!(relative("/a/b/c", "/x"), Path::new("../../../x"));
+        assert_eq!(relative("/", "/a"), Path::new("a"));
+        assert_eq!(relative("/a/bc", "/a/b/c"), Path::new("../b/c"));
+    }
+
+    #[test]
+    fn relative_links_survive_links_in_the_directories_leading_to_them() {
+        let directory = env::temp_dir().join(format!("nfm-test-link-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("real/target")).unwrap();
+        fs::create_dir_all(directory.join("links")).unwrap();
+        symlink(directory.join("real"), directory.join("alias")).unwrap();
+
+        let path = directory.join("links/target");
+        create(LinkKind::Relative, &directory.join("alias/target"), &path).unwrap();
+
+        assert_eq!(read_link(&path).unwrap(), Path::new("../real/target"));
+        assert!(fs::metadata(&path).unwrap().is_dir());
+        assert!(expect_link(LinkKind::Relative, &path).is_ok());
+        assert!(expect_link(LinkKind::Hard, &path).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
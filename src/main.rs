@@ -3,12 +3,45 @@ use std::io::Result;
 use nfm::NFM;
 
 mod action;
+mod completion;
+mod config;
+mod details;
 mod entry;
+mod glob;
+mod grep;
+mod history;
+mod journal;
+mod key;
+mod keymap;
+mod layout;
+mod line_editor;
+mod link;
+mod mime;
 mod mode;
+mod name;
 mod nfm;
+mod opener;
+mod permissions;
+mod preview;
+mod rename_plan;
+mod results;
+mod screen;
+mod search;
+mod sort;
+mod tab;
+mod text;
+mod theme;
+mod time;
+mod transfer;
+mod trash;
+mod users;
+mod walk;
 mod window;
+mod xdg;
 
 fn main() -> Result<()> {
+    screen::install_panic_hook();
+
     let mut nfm = NFM::new();
     nfm.run()
 }
@@ -0,0 +1,88 @@
+use std::{
+    fs::{metadata, File},
+    io::Read,
+    path::Path,
+};
+
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (257, b"ustar", "application/x-tar"),
+    (0, b"\x7fELF", "application/x-executable"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+];
+
+/// Guesses the MIME type of the file at `path` from its leading bytes.
+///
+/// Files without a known signature are reported as `text/plain` when their
+/// head is valid UTF-8 without NUL bytes, and `application/octet-stream`
+/// otherwise. Only regular files are read: pipes and devices could block or
+/// never end, and are reported as `application/octet-stream` as they are.
+pub fn sniff(path: &Path) -> Option<&'static str> {
+    if !metadata(path).ok()?.is_file() {
+        return Some("application/octet-stream");
+    }
+
+    let mut head = Vec::with_capacity(512);
+    File::open(path)
+        .ok()?
+        .take(512)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    Some(from_bytes(&head))
+}
+
+pub fn from_bytes(head: &[u8]) -> &'static str {
+    for (offset, signature, mime) in SIGNATURES {
+        if head.get(*offset..offset + signature.len()) == Some(*signature) {
+            return mime;
+        }
+    }
+
+    if head.len() >= 12 && &head[0..4] == b"RIFF" {
+        match &head[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            b"AVI " => return "video/x-msvideo",
+            _ => {}
+        }
+    }
+
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return "video/mp4";
+    }
+
+    if is_text(head) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Whether `head` looks like text: no NUL bytes and valid UTF-8, allowing a
+/// multi-byte sequence to be cut off at the end.
+pub fn is_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
@@ -1,9 +1,23 @@
-#[derive(PartialEq, Clone)]
+use serde::Deserialize;
+
+#[derive(Deserialize, PartialEq, Eq, Hash, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum Mode {
     Normal,
     Rename,
+    BulkRename,
     Remove,
+    Delete,
+    Trash,
+    Purge,
     Add,
+    Link,
+    Permissions,
     Search,
+    Find,
+    Grep,
+    Mark,
+    Conflict,
     Help,
+    Message,
 }
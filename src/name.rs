@@ -0,0 +1,126 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt::Write,
+    io::{Error, ErrorKind, Result},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+};
+
+/// Renders `name` for the screen. Bytes that are not valid UTF-8 and the
+/// bytes of control characters are written as `\xNN` and backslashes as
+/// `\\`, so that every name takes a single line and no two names look the
+/// same.
+pub fn display(name: &OsStr) -> String {
+    let mut text = String::new();
+
+    for chunk in name.as_bytes().utf8_chunks() {
+        for character in chunk.valid().chars() {
+            if character.is_control() && (character as u32) < 0x100 {
+                let mut buffer = [0; 2];
+
+                for byte in character.encode_utf8(&mut buffer).bytes() {
+                    let _ = write!(text, "\\x{:02x}", byte);
+                }
+            } else if character == '\\' {
+                text.push_str("\\\\");
+            } else {
+                text.push(character);
+            }
+        }
+
+        for byte in chunk.invalid() {
+            let _ = write!(text, "\\x{:02x}", byte);
+        }
+    }
+
+    text
+}
+
+/// Turns a name typed at a prompt back into bytes, reading `\xNN` as the
+/// byte it stands for and `\\` as a backslash, the way `display` writes
+/// them. A slash or NUL byte written that way is refused, as no name can hold
+/// one.
+pub fn parse(text: &str) -> Result<OsString> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let digit = |byte: u8| (byte as char).to_digit(16);
+        let escaped = match tail {
+            [b'\\', ..] if byte == b'\\' => Some((b'\\', 1)),
+            [b'x', high, low, ..] if byte == b'\\' => digit(*high)
+                .zip(digit(*low))
+                .map(|(high, low)| ((high * 16 + low) as u8, 3)),
+            _ => None,
+        };
+
+        match escaped {
+            Some((b'/' | b'\0', _)) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "names cannot hold a slash or NUL byte",
+                ));
+            }
+            Some((escaped, length)) => {
+                bytes.push(escaped);
+                rest = &tail[length..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    Ok(OsString::from_vec(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) {
+        let name = OsStr::from_bytes(bytes);
+        assert_eq!(parse(&display(name)).unwrap(), name, "{:?}", display(name));
+    }
+
+    #[test]
+    fn plain_names_are_shown_as_they_are() {
+        assert_eq!(display(OsStr::new("notes.txt")), "notes.txt");
+        assert_eq!(display(OsStr::new("café ☕")), "café ☕");
+    }
+
+    #[test]
+    fn unprintable_bytes_and_backslashes_are_escaped() {
+        assert_eq!(display(OsStr::from_bytes(b"a\nb\tc")), "a\\x0ab\\x09c");
+        assert_eq!(display(OsStr::from_bytes(b"\xff\xfe")), "\\xff\\xfe");
+        assert_eq!(display(OsStr::new("a\\b")), "a\\\\b");
+        assert_eq!(display(OsStr::new("\\x41")), "\\\\x41");
+        assert_eq!(display(OsStr::new("\u{85}")), "\\xc2\\x85");
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        round_trip(b"plain");
+        round_trip(b"line\nbreak");
+        round_trip(b"\x1b[31mred");
+        round_trip(b"latin1 \xe9t\xe9");
+        round_trip(b"back\\slash");
+        round_trip(b"\\x41 is not A");
+        round_trip(b"trailing\\");
+        round_trip("\u{85}next line".as_bytes());
+    }
+
+    #[test]
+    fn lone_backslashes_are_kept() {
+        assert_eq!(parse("a\\b").unwrap(), "a\\b");
+        assert_eq!(parse("a\\x4").unwrap(), "a\\x4");
+        assert_eq!(parse("\\xzz").unwrap(), "\\xzz");
+    }
+
+    #[test]
+    fn escaped_slashes_and_nul_are_refused() {
+        assert!(parse("a\\x2fb").is_err());
+        assert!(parse("a\\x00b").is_err());
+        assert_eq!(parse("a\\\\x2fb").unwrap(), "a\\x2fb");
+    }
+}
@@ -1,8 +1,13 @@
 use std::{
-    env::{current_dir, set_current_dir},
-    fs::{create_dir, read_dir, remove_dir_all, remove_file, rename},
-    io::{stdout, Result, Write},
-    process::Command,
+    collections::HashSet,
+    env,
+    ffi::OsString,
+    fs::{canonicalize, create_dir, read, read_dir, rename, symlink_metadata, File},
+    io::{stdout, Error, ErrorKind, Result, Write},
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process::Child,
     time::Duration,
 };
 
@@ -14,33 +19,174 @@ use crossterm::{
     ExecutableCommand, QueueableCommand,
 };
 
-use crate::{action::Action, entry::Entry, mode::Mode, window::Window};
+use crate::{
+    action::Action,
+    completion,
+    config::Config,
+    details::Details,
+    entry::{Entry, EntryMark},
+    glob,
+    grep::{self, GrepMatch},
+    history::History,
+    journal::{Journal, Operation},
+    key::KeyChord,
+    keymap::{format_sequence, Keymap, Resolution},
+    layout::{Layout, Panes},
+    line_editor::LineEditor,
+    link::{self, LinkKind},
+    mode::Mode,
+    name,
+    opener::{Launch, Opener},
+    permissions::PermissionEditor,
+    preview::{Class, Preview, Previewer},
+    rename_plan::{self, Listing, RenamePlan},
+    results::{ResultList, Row},
+    screen,
+    search::{Matcher, MatcherKind, Matches, Search},
+    sort::{Remembered, Sort},
+    tab::{self, Tab},
+    text,
+    theme::Theme,
+    transfer::{self, Paste, Register, RegisterKind},
+    trash::{Trash, TrashItem},
+    walk::Walk,
+    window::Window,
+};
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct NFM {
+    path: PathBuf,
     selection: u16,
     scroll: u16,
+    tabs: Vec<Tab>,
+    /// Index of the tab in view.
+    tab: usize,
+    /// The listing that is not in use in the dual layout.
+    other: Tab,
+    /// Whether the listing in use is the right one in the dual layout.
+    right: bool,
     entries: Vec<Entry>,
     actions: Vec<Action>,
     mode: Mode,
     show_hidden: bool,
-    rename_buffer: String,
-    add_buffer: String,
-    search_buffer: String,
+    follow_links: bool,
+    layout: Layout,
+    preview: Option<Preview>,
+    previewer: Previewer,
+    rename_prompt: LineEditor,
+    add_prompt: LineEditor,
+    link_prompt: LineEditor,
+    link_kind: LinkKind,
+    permissions: Option<PermissionEditor>,
+    search_prompt: LineEditor,
+    mark_prompt: LineEditor,
+    /// The query of a file or content search.
+    results_prompt: LineEditor,
+    history: History,
+    results_selection: u16,
+    finder: Option<Walk<PathBuf>>,
+    matches: Matches,
+    grep: Option<Walk<GrepMatch>>,
+    /// What the running content search looks for.
+    grep_query: String,
+    marked: HashSet<OsString>,
+    rename_queue: Vec<OsString>,
+    register: Option<Register>,
+    paste: Option<Paste>,
+    trash_items: Vec<TrashItem>,
+    trash_selection: u16,
+    journal: Journal,
+    rename_plan: Option<RenamePlan>,
+    sort: Sort,
+    /// The configured sort, for directories without one of their own.
+    default_sort: Sort,
+    remembered: Remembered,
+    details: Details,
+    search: Search,
+    opener: Opener,
+    /// Commands launched in the background, reaped once they exit.
+    detached: Vec<Child>,
+    keymap: Keymap,
+    theme: Theme,
+    message: String,
     should_close: bool,
 }
 
 impl NFM {
     pub fn new() -> Self {
+        let (config, message) = match Config::load() {
+            Ok(config) => (config, None),
+            Err(error) => (Config::default(), Some(error.to_string())),
+        };
+
+        let (remembered, message) = match Remembered::load() {
+            Ok(remembered) => (remembered, message),
+            Err(error) => (Remembered::default(), message.or(Some(error.to_string()))),
+        };
+
+        let (history, message) = match History::load() {
+            Ok(history) => (history, message),
+            Err(error) => (History::default(), message.or(Some(error.to_string()))),
+        };
+
+        let path = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let sort = match remembered.get(&path) {
+            Some(sort) if config.sort.remember => sort,
+            _ => config.sort,
+        };
+
         Self {
+            tabs: vec![Tab::new(path.clone(), sort)],
+            tab: 0,
+            other: Tab::new(path.clone(), sort),
+            right: false,
+            path,
             selection: 0,
             scroll: 0,
             entries: Vec::new(),
             actions: Vec::new(),
-            mode: Mode::Normal,
-            show_hidden: false,
-            rename_buffer: String::new(),
-            add_buffer: String::new(),
-            search_buffer: String::new(),
+            mode: if message.is_some() {
+                Mode::Message
+            } else {
+                Mode::Normal
+            },
+            show_hidden: config.show_hidden,
+            follow_links: config.follow_links,
+            layout: config.layout,
+            preview: None,
+            previewer: Previewer::spawn(),
+            rename_prompt: LineEditor::new("rename"),
+            add_prompt: LineEditor::new("add"),
+            link_prompt: LineEditor::new("link"),
+            link_kind: LinkKind::Absolute,
+            permissions: None,
+            search_prompt: LineEditor::new("search"),
+            mark_prompt: LineEditor::new("mark"),
+            results_prompt: LineEditor::new("results"),
+            history,
+            results_selection: 0,
+            finder: None,
+            matches: Matches::default(),
+            grep: None,
+            grep_query: String::new(),
+            marked: HashSet::new(),
+            rename_queue: Vec::new(),
+            register: None,
+            paste: None,
+            trash_items: Vec::new(),
+            trash_selection: 0,
+            journal: Journal::default(),
+            rename_plan: None,
+            sort,
+            default_sort: config.sort,
+            remembered,
+            details: config.details,
+            search: config.search,
+            opener: Opener::new(config.opener),
+            detached: Vec::new(),
+            keymap: config.keys.keymap(),
+            theme: config.theme,
+            message: message.unwrap_or_default(),
             should_close: false,
         }
     }
@@ -50,6 +196,7 @@ impl NFM {
 
         stdout()
             .execute(terminal::EnterAlternateScreen)?
+            .execute(event::EnableBracketedPaste)?
             .execute(cursor::Hide)?
             .execute(cursor::MoveTo(0, 0))?
             .execute(terminal::Clear(terminal::ClearType::All))?;
@@ -59,79 +206,63 @@ impl NFM {
     }
 
     fn deinitialize(&mut self) -> Result<()> {
-        terminal::disable_raw_mode()?;
-
-        stdout()
-            .execute(cursor::Show)?
-            .execute(terminal::LeaveAlternateScreen)?;
-
-        Ok(())
+        screen::restore()
     }
 
     fn handle_key_event(&mut self, event: &event::KeyEvent) {
-        match self.mode {
-            Mode::Normal => match event.code {
-                event::KeyCode::Esc => self.actions.push(Action::Close),
-                event::KeyCode::Up => {
-                    if event.modifiers.bits() & 2 != 0 {
-                        self.actions.push(Action::ScrollUp)
-                    } else {
-                        self.actions.push(Action::MoveUp)
-                    }
+        match self.keymap.resolve(&self.mode, KeyChord::from(event)) {
+            Resolution::Bound(action) => self.actions.push(action),
+            Resolution::Pending => {}
+            Resolution::Unbound => {
+                if matches!(
+                    self.mode,
+                    Mode::Rename
+                        | Mode::Add
+                        | Mode::Link
+                        | Mode::Permissions
+                        | Mode::Search
+                        | Mode::Find
+                        | Mode::Grep
+                        | Mode::Mark
+                ) {
+                    self.actions.push(Action::Input(KeyChord::from(event)));
                 }
-                event::KeyCode::Down => {
-                    if event.modifiers.bits() & 2 != 0 {
-                        self.actions.push(Action::ScrollDown)
-                    } else {
-                        self.actions.push(Action::MoveDown)
-                    }
-                }
-                event::KeyCode::Enter => self.actions.push(Action::Open),
-                event::KeyCode::Backspace => self.actions.push(Action::Back),
-                event::KeyCode::Home => self.actions.push(Action::Home),
-                event::KeyCode::End => self.actions.push(Action::End),
-                event::KeyCode::Char('h') => self.actions.push(Action::ToggleHidden),
-                event::KeyCode::Char('r') => self.actions.push(Action::Rename),
-                event::KeyCode::Char('d') => self.actions.push(Action::Remove),
-                event::KeyCode::Char('a') => self.actions.push(Action::Add),
-                event::KeyCode::Char('/') => self.actions.push(Action::Search),
-                event::KeyCode::Char('?') => self.actions.push(Action::ToggleHelp),
-                _ => {}
-            },
-
-            Mode::Rename => match event.code {
-                event::KeyCode::Esc => self.actions.push(Action::Close),
-                input => self.actions.push(Action::Input(input)),
-            },
-
-            Mode::Remove => match event.code {
-                event::KeyCode::Esc => self.actions.push(Action::Close),
-                event::KeyCode::Enter => self.actions.push(Action::Remove),
-                _ => {}
-            },
-
-            Mode::Add => match event.code {
-                event::KeyCode::Esc => self.actions.push(Action::Close),
-                event::KeyCode::Enter => self.actions.push(Action::Add),
-                input => self.actions.push(Action::Input(input)),
-            },
-
-            Mode::Search => match event.code {
-                event::KeyCode::Esc => self.actions.push(Action::Close),
-                input => self.actions.push(Action::Input(input)),
-            },
+            }
+        }
+    }
 
-            Mode::Help => match event.code {
-                event::KeyCode::Esc | event::KeyCode::Char('?') => self.actions.push(Action::Close),
-                _ => {}
-            },
+    /// The prompt being typed in, if any.
+    fn prompt_mut(&mut self) -> Option<&mut LineEditor> {
+        match self.mode {
+            Mode::Rename => Some(&mut self.rename_prompt),
+            Mode::Add => Some(&mut self.add_prompt),
+            Mode::Link => Some(&mut self.link_prompt),
+            Mode::Search => Some(&mut self.search_prompt),
+            Mode::Find | Mode::Grep => Some(&mut self.results_prompt),
+            Mode::Mark => Some(&mut self.mark_prompt),
+            Mode::Permissions => self
+                .permissions
+                .as_mut()
+                .and_then(PermissionEditor::field_mut),
+            _ => None,
         }
     }
 
     fn handle_event(&mut self) -> Result<()> {
         if event::poll(Duration::from_millis(0))? {
             match event::read()? {
-                event::Event::Key(key_event) => Ok(self.handle_key_event(&key_event)),
+                event::Event::Key(key_event) => {
+                    self.handle_key_event(&key_event);
+                    Ok(())
+                }
+                event::Event::Paste(text) => {
+                    if let Some(prompt) = self.prompt_mut() {
+                        prompt.paste(&text);
+                        self.actions.push(Action::Redraw);
+                    }
+
+                    Ok(())
+                }
                 event::Event::Resize(..) => {
                     while self.selection.saturating_sub(self.scroll) > terminal::size()?.1 - 4 {
                         self.selection = self.selection.saturating_sub(1);
@@ -147,40 +278,439 @@ impl NFM {
         }
     }
 
-    fn fetch_entries_sorted(&self) -> Result<Vec<Entry>> {
-        let mut entries = read_dir(".")?
-            .map(|e| Entry::new(e.unwrap()))
-            .filter(|e| {
-                let file_name = e.base.file_name().into_string().unwrap().to_lowercase();
-                file_name.contains(&self.search_buffer.to_lowercase())
-                    && if self.show_hidden {
-                        true
-                    } else {
-                        !file_name.starts_with('.')
-                    }
+    /// Hands what background work has produced over to the main loop.
+    fn handle_background(&mut self) -> Result<()> {
+        self.detached
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        let changed = self.finder.as_mut().is_some_and(Walk::poll)
+            | self.grep.as_mut().is_some_and(Walk::poll);
+
+        if changed && matches!(self.mode, Mode::Find | Mode::Grep) {
+            self.actions.push(Action::Redraw);
+        }
+
+        if let Some(preview) = self.previewer.poll() {
+            self.preview = Some(preview);
+
+            // Only the pane is redrawn, leaving the listing and any prompt
+            // as they are.
+            if self.layout != Layout::Single
+                && matches!(
+                    self.mode,
+                    Mode::Normal
+                        | Mode::Search
+                        | Mode::Mark
+                        | Mode::Rename
+                        | Mode::Add
+                        | Mode::Link
+                        | Mode::Remove
+                        | Mode::Delete
+                        | Mode::Conflict
+                )
+            {
+                let selected = self
+                    .entries
+                    .get(self.selection as usize)
+                    .map(|entry| entry.base.path());
+
+                stdout().queue(cursor::SavePosition)?;
+                self.draw_preview(selected.as_deref())?;
+                stdout().queue(cursor::RestorePosition)?.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn panes(&self) -> Result<Panes> {
+        let mut panes = self.layout.panes(terminal::size()?.0);
+
+        if self.right {
+            if let Some(other) = &mut panes.other {
+                mem::swap(other, &mut panes.list);
+            }
+        }
+
+        Ok(panes)
+    }
+
+    /// Column where the text of the prompt being edited starts. Renaming and
+    /// adding happen inside the listing, which may not start at the left edge.
+    fn prompt_column(&self) -> Result<u16> {
+        Ok(if matches!(self.mode, Mode::Rename | Mode::Add) {
+            self.panes()?.list.0 + 4
+        } else {
+            4
+        })
+    }
+
+    /// Queues the preview of `selected` in its pane, asking for it in the
+    /// background if it is not ready yet.
+    fn draw_preview(&self, selected: Option<&Path>) -> Result<()> {
+        let Some((column, width)) = self.panes()?.preview else {
+            return Ok(());
+        };
+
+        let width = width.saturating_sub(2) as usize;
+        let rows = terminal::size()?.1.saturating_sub(3);
+
+        let lines = match (selected, &self.preview) {
+            (None, _) => Vec::new(),
+            (Some(path), Some(preview)) if preview.is_current(path) => preview.lines.clone(),
+            (Some(path), _) => {
+                self.previewer.request(path.to_path_buf());
+                vec![vec![(Class::Muted, "Loading".to_string())]]
+            }
+        };
+
+        for row in 0..rows {
+            stdout()
+                .queue(cursor::MoveTo(column, row + 2))?
+                .queue(style::PrintStyledContent("│".with(self.theme.muted)))?
+                .queue(style::Print(" ".repeat(width + 1)))?
+                .queue(cursor::MoveTo(column + 2, row + 2))?;
+
+            let Some(line) = lines.get(row as usize) else {
+                continue;
+            };
+
+            let mut left = width;
+
+            for (class, text) in line {
+                let text = text::truncate(text, left).to_string();
+                left -= text::width(&text);
+
+                stdout().queue(style::PrintStyledContent(match class {
+                    Class::Plain => text.stylize(),
+                    Class::Keyword => text.with(self.theme.accent),
+                    Class::String => text.green(),
+                    Class::Number => text.yellow(),
+                    Class::Comment => text.with(self.theme.muted).italic(),
+                    Class::Directory => text.dark_blue().bold(),
+                    Class::Muted => text.with(self.theme.muted).italic(),
+                }))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Queues the tabs at the start of the header when there is more than
+    /// one, and returns how many columns they take.
+    fn draw_tab_bar(&self) -> Result<usize> {
+        if self.tabs.len() < 2 {
+            return Ok(0);
+        }
+
+        let mut width = 0;
+
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let label = if index == self.tab {
+                format!(" {} {} ", index + 1, tab::title(&self.path))
+            } else {
+                format!(" {} {} ", index + 1, tab::title(&tab.path))
+            };
+
+            width += text::width(&label);
+
+            stdout().queue(style::PrintStyledContent(if index == self.tab {
+                label
+                    .with(self.theme.accent)
+                    .on(self.theme.selection)
+                    .bold()
+            } else {
+                label.with(self.theme.muted)
+            }))?;
+        }
+
+        stdout().queue(style::PrintStyledContent("│".with(self.theme.muted)))?;
+
+        Ok(width + 1)
+    }
+
+    /// Goes to `path` in the tab in view, with the sort remembered for it
+    /// when sorts are remembered.
+    fn set_path(&mut self, path: PathBuf) {
+        if self.default_sort.remember {
+            self.sort = self.remembered.get(&path).unwrap_or(self.default_sort);
+        }
+
+        self.path = path;
+        self.marked.clear();
+    }
+
+    /// Moves the state of the directory in view out into a `Tab`.
+    fn take_state(&mut self) -> Tab {
+        Tab {
+            path: self.path.clone(),
+            selection: self.selection,
+            scroll: self.scroll,
+            search_buffer: self.search_prompt.take(),
+            sort: self.sort,
+            marked: mem::take(&mut self.marked),
+        }
+    }
+
+    /// Brings the directory `tab` holds into view.
+    fn put_state(&mut self, tab: Tab) {
+        self.path = tab.path;
+        self.selection = tab.selection;
+        self.scroll = tab.scroll;
+        self.search_prompt.set(tab.search_buffer);
+        self.sort = tab.sort;
+        self.marked = tab.marked;
+    }
+
+    /// Stores the state of the tab in view into `tabs`.
+    fn store_tab(&mut self) {
+        let state = self.take_state();
+        self.tabs[self.tab] = state;
+    }
+
+    /// Brings the state of tab `self.tab` back into view.
+    fn load_tab(&mut self) {
+        let tab = mem::replace(
+            &mut self.tabs[self.tab],
+            Tab::new(PathBuf::new(), self.sort),
+        );
+        self.put_state(tab);
+    }
+
+    /// Queues the listing that is not in use in the dual layout, with both
+    /// listings boxed and the one in use titled in the accent color.
+    fn draw_other(&self) -> Result<()> {
+        let panes = self.panes()?;
+        let Some((column, width)) = panes.other else {
+            return Ok(());
+        };
+
+        let rows = terminal::size()?.1.saturating_sub(3);
+
+        for ((column, width), path, active) in [
+            (panes.list, &self.path, true),
+            ((column, width), &self.other.path, false),
+        ] {
+            Window::new((column - 1, 1), (width + 1, rows)).draw()?;
+
+            let title = format!(" {} ", tab::title(path));
+            let title = text::truncate(&title, (width as usize).saturating_sub(2)).to_string();
+
+            stdout()
+                .queue(cursor::MoveTo(column + 1, 1))?
+                .queue(style::PrintStyledContent(if active {
+                    title.with(self.theme.accent).bold()
+                } else {
+                    title.with(self.theme.muted)
+                }))?;
+        }
+
+        let mut entries = match self.fetch_entries_sorted(
+            &self.other.path,
+            &self.other.sort,
+            &self.other.search_buffer,
+        ) {
+            Ok(entries) => entries,
+            Err(error) => return self.draw_unlisted(column, width, error),
+        };
+
+        for entry in entries.iter_mut() {
+            if self.other.marked.contains(&entry.base.file_name()) {
+                entry.mark = EntryMark::Selected;
+            }
+        }
+
+        self.details.fill(&mut entries, width as usize);
+
+        if entries.is_empty() {
+            stdout()
+                .queue(cursor::MoveTo(column, 2))?
+                .queue(style::PrintStyledContent(
+                    " Empty".with(self.theme.muted).italic(),
+                ))?;
+        }
+
+        let visible = rows.saturating_sub(1) as usize;
+
+        for (row, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(self.other.scroll as usize)
+            .take(visible)
+            .enumerate()
+        {
+            // Only the listing in use shows its selection.
+            stdout().queue(cursor::MoveTo(column, row as u16 + 2))?;
+            entry.draw(u16::MAX, index, width as usize, &self.theme)?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues the listing of the parent directory in its pane, with the
+    /// current directory selected.
+    fn draw_parent(&self) -> Result<()> {
+        let Some((column, width)) = self.panes()?.parent else {
+            return Ok(());
+        };
+
+        let rows = terminal::size()?.1.saturating_sub(3);
+
+        let Some(parent) = self.path.parent() else {
+            return Ok(());
+        };
+
+        let mut entries = match read_dir(parent) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(Entry::new)
+                .filter(|entry| {
+                    self.show_hidden || !entry.base.file_name().as_bytes().starts_with(b".")
+                })
+                .collect::<Vec<_>>(),
+            Err(error) => return self.draw_unlisted(column, width, error),
+        };
+
+        self.sort.apply(&mut entries);
+
+        let selected = entries
+            .iter()
+            .position(|entry| Some(entry.base.file_name().as_os_str()) == self.path.file_name())
+            .unwrap_or(0);
+        let scroll = selected.saturating_sub(rows as usize / 2);
+        let width = (width as usize).saturating_sub(2);
+
+        for (row, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(rows as usize)
+            .enumerate()
+        {
+            let name = name::display(&entry.base.file_name());
+            let text = format!(" {}", text::fit(&name, width));
+
+            let text = if entry
+                .base
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_dir())
+            {
+                text.dark_blue().bold()
+            } else {
+                text.stylize()
+            };
+
+            stdout()
+                .queue(cursor::MoveTo(column, row as u16 + 2))?
+                .queue(style::PrintStyledContent(if index == selected {
+                    text.on(self.theme.selection)
+                } else {
+                    text
+                }))?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues why a pane at `column` that is `width` wide could not list its
+    /// directory, in place of the listing.
+    fn draw_unlisted(&self, column: u16, width: u16, error: Error) -> Result<()> {
+        let text = format!(" Cannot list: {}", error);
+        let text = text::truncate(&text, (width as usize).saturating_sub(1)).to_string();
+
+        stdout()
+            .queue(cursor::MoveTo(column, 2))?
+            .queue(style::PrintStyledContent(
+                text.with(self.theme.muted).italic(),
+            ))?;
+
+        Ok(())
+    }
+
+    /// The entries of `path` in `sort` order, keeping only those that
+    /// match `filter`.
+    fn fetch_entries_sorted(&self, path: &Path, sort: &Sort, filter: &str) -> Result<Vec<Entry>> {
+        let mut entries = read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(Entry::new)
+            .filter(|entry| {
+                self.show_hidden || !entry.base.file_name().as_bytes().starts_with(b".")
             })
             .collect::<Vec<_>>();
 
-        entries.sort_by_key(|e| e.base.file_name());
+        sort.apply(&mut entries);
+
+        // A pattern that does not compile yet, such as a regex being typed,
+        // filters nothing out.
+        if let Ok(matcher) = Matcher::new(&self.search, filter) {
+            let mut matched = entries
+                .into_iter()
+                .filter_map(|mut entry| {
+                    let found = matcher.find(&name::display(&entry.base.file_name()))?;
+                    entry.highlights = found.positions;
+                    Some((found.score, entry))
+                })
+                .collect::<Vec<_>>();
+
+            if self.search.matcher == MatcherKind::Fuzzy {
+                matched.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            }
+
+            entries = matched.into_iter().map(|(_, entry)| entry).collect();
+        }
+
+        for entry in entries.iter_mut() {
+            entry.follow_links = self.follow_links;
+        }
 
         Ok(entries)
     }
 
     fn draw(&self) -> Result<Vec<Entry>> {
-        let entries = self.fetch_entries_sorted()?;
-        let current_dir = current_dir()?;
+        let mut entries =
+            self.fetch_entries_sorted(&self.path, &self.sort, self.search_prompt.text())?;
+
+        for entry in entries.iter_mut() {
+            if self.marked.contains(&entry.base.file_name()) {
+                entry.mark = if matches!(self.mode, Mode::Remove | Mode::Delete) {
+                    EntryMark::Removal
+                } else {
+                    EntryMark::Selected
+                };
+            }
+        }
+
+        let panes = self.panes()?;
+        self.details.fill(&mut entries, panes.list.1 as usize);
+
+        let hint = match &self.register {
+            Some(register) => format!(
+                "{} {}",
+                register.paths.len(),
+                match register.kind {
+                    RegisterKind::Copy => "to copy",
+                    RegisterKind::Cut => "to move",
+                }
+            ),
+            None => "Press '?' to open help menu".to_string(),
+        };
+        let hint = format!("By {} │ {}", self.sort.label(), hint);
+        let path = name::display(self.path.as_os_str());
 
         stdout()
             .queue(terminal::Clear(terminal::ClearType::All))?
-            .queue(cursor::SavePosition)?
-            .queue(cursor::MoveTo(0, 0))?
+            .queue(cursor::MoveTo(0, 0))?;
+
+        let tab_bar = self.draw_tab_bar()?;
+
+        stdout()
             .queue(style::PrintStyledContent(
                 format!(
                     " In: {}{:>padding$}",
-                    current_dir.to_str().unwrap().blue().italic(),
-                    "Press '?' to open help menu",
-                    padding =
-                        terminal::size()?.0 as usize - current_dir.to_str().unwrap().len() - 6,
+                    path.clone().with(self.theme.accent).italic(),
+                    hint,
+                    padding = (terminal::size()?.0 as usize)
+                        .saturating_sub(text::width(&path) + 6 + tab_bar),
                 )
                 .stylize(),
             ))?
@@ -199,96 +729,231 @@ impl NFM {
                 continue;
             }
 
-            entry.draw(self.selection, index)?;
-            stdout().queue(cursor::MoveToNextLine(1))?;
+            stdout().queue(cursor::MoveTo(panes.list.0, index as u16 - self.scroll + 2))?;
+            entry.draw(self.selection, index, panes.list.1 as usize, &self.theme)?;
         }
 
         if !drawn {
-            stdout().queue(style::PrintStyledContent(" Empty".grey().italic()))?;
+            stdout()
+                .queue(cursor::MoveTo(panes.list.0, 2))?
+                .queue(style::PrintStyledContent(
+                    " Empty".with(self.theme.muted).italic(),
+                ))?;
+        }
+
+        self.draw_parent()?;
+        self.draw_other()?;
+
+        if panes.preview.is_some() {
+            self.draw_preview(
+                entries
+                    .get(self.selection as usize)
+                    .map(|entry| entry.base.path())
+                    .as_deref(),
+            )?;
+        }
+
+        self.draw_menu()?;
+
+        match self.mode {
+            Mode::Mark => self.redraw_mark_prompt()?,
+            Mode::Link => self.redraw_link_prompt()?,
+            _ => self.redraw_search_prompt()?,
         }
 
-        stdout().queue(cursor::MoveTo(4, terminal::size()?.1 - 1))?;
+        match self.mode {
+            Mode::Rename => self.redraw_rename_prompt()?,
+            Mode::Add => self.redraw_add_prompt()?,
+            _ => {}
+        }
 
-        self.redraw_search_buffer()?;
-        stdout().queue(cursor::RestorePosition)?.flush()?;
+        stdout().flush()?;
         Ok(entries)
     }
 
-    fn move_left(&self) -> Result<()> {
-        if cursor::position()?.0 > 4 {
-            stdout().execute(cursor::MoveLeft(1))?;
-        }
+    /// Queues the completion menu of the add or link prompt, if it offers a
+    /// choice.
+    fn draw_menu(&self) -> Result<()> {
+        let (menu, anchor) = match self.mode {
+            Mode::Add => (
+                self.add_prompt.menu(),
+                (self.prompt_column()?, self.entries.len() as u16 + 2),
+            ),
+            Mode::Link => (self.link_prompt.menu(), (4, terminal::size()?.1 - 1)),
+            _ => return Ok(()),
+        };
 
-        Ok(())
+        match menu {
+            Some(menu) => menu.draw(anchor, &self.theme),
+            None => Ok(()),
+        }
     }
 
-    fn move_right(&self, buffer: &String) -> Result<()> {
-        if cursor::position()?.0 < buffer.len() as u16 + 4 {
-            stdout().execute(cursor::MoveRight(1))?;
+    /// Redraws the add or link prompt with its menu after an edit, or the
+    /// whole screen when the edit closed a menu that was shown.
+    fn redraw_path_prompt(&mut self, menu_was_shown: bool) -> Result<()> {
+        let prompt = match self.mode {
+            Mode::Add => &self.add_prompt,
+            _ => &self.link_prompt,
+        };
+
+        if menu_was_shown && prompt.menu().is_none() {
+            self.entries = self.draw()?;
+            return Ok(());
         }
 
-        Ok(())
+        self.draw_menu()?;
+
+        match self.mode {
+            Mode::Add => self.redraw_add_prompt(),
+            _ => self.redraw_link_prompt(),
+        }
     }
 
-    fn redraw_rename_buffer(&self, extra_size: usize) -> Result<()> {
+    /// Queues the rename prompt over the selected entry, leaving the cursor
+    /// in it.
+    fn redraw_rename_prompt(&self) -> Result<()> {
+        let column = self.prompt_column()?;
+        let row = self.selection - self.scroll + 2;
+        let width = (self.panes()?.list.1 as usize).saturating_sub(4);
+
         stdout()
-            .execute(cursor::SavePosition)?
-            .execute(cursor::MoveToColumn(4))?
-            .execute(style::Print(
-                " ".repeat(self.rename_buffer.len() + extra_size).on_black(),
+            .queue(cursor::MoveTo(column, row))?
+            .queue(style::PrintStyledContent(
+                text::fit(self.rename_prompt.text(), width)
+                    .with(self.theme.accent)
+                    .on(self.theme.selection)
+                    .bold(),
             ))?
-            .execute(cursor::MoveToColumn(4))?
-            .execute(style::PrintStyledContent(
-                self.rename_buffer.clone().blue().on_black().bold(),
+            .queue(cursor::MoveTo(
+                column + self.rename_prompt.cursor_column() as u16,
+                row,
             ))?
-            .execute(cursor::RestorePosition)?;
-
-        Ok(())
+            .flush()
     }
 
-    fn redraw_add_buffer(&self, extra_size: usize) -> Result<()> {
+    /// Queues the prompt for a new entry below the last one, leaving the
+    /// cursor in it.
+    fn redraw_add_prompt(&self) -> Result<()> {
+        let column = self.prompt_column()?;
+        let row = self.entries.len() as u16 + 2;
+        let width = (self.panes()?.list.1 as usize).saturating_sub(4);
+
         stdout()
-            .execute(cursor::SavePosition)?
-            .execute(cursor::MoveToColumn(4))?
-            .execute(style::Print(" ".repeat(self.add_buffer.len() + extra_size)))?
-            .execute(cursor::MoveToColumn(1))?
-            .execute(style::Print("+"))?
-            .execute(cursor::MoveRight(2))?
-            .execute(style::PrintStyledContent(
-                self.add_buffer.clone().blue().bold(),
+            .queue(cursor::MoveTo(column - 3, row))?
+            .queue(style::Print("+  "))?
+            .queue(style::PrintStyledContent(
+                text::fit(self.add_prompt.text(), width)
+                    .with(self.theme.accent)
+                    .bold(),
+            ))?
+            .queue(cursor::MoveTo(
+                column + self.add_prompt.cursor_column() as u16,
+                row,
             ))?
-            .execute(cursor::RestorePosition)?;
+            .flush()
+    }
 
-        Ok(())
+    fn redraw_mark_prompt(&self) -> Result<()> {
+        let row = terminal::size()?.1 - 1;
+
+        stdout()
+            .queue(cursor::MoveTo(0, row))?
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .queue(cursor::MoveTo(1, row))?
+            .queue(style::Print("*  "))?
+            .queue(style::PrintStyledContent(
+                self.mark_prompt
+                    .text()
+                    .to_string()
+                    .with(self.theme.accent)
+                    .bold(),
+            ))?
+            .queue(cursor::MoveTo(
+                4 + self.mark_prompt.cursor_column() as u16,
+                row,
+            ))?
+            .flush()
     }
 
-    fn redraw_search_buffer(&self) -> Result<()> {
-        let previous_cursor_position = cursor::position()?;
+    fn redraw_link_prompt(&self) -> Result<()> {
+        let label = self.link_kind.name();
+        let (width, row) = terminal::size()?;
+        let row = row - 1;
 
         stdout()
-            .execute(cursor::MoveToColumn(4))?
-            .execute(style::Print(
-                " ".repeat(self.search_buffer.len()).underlined(),
+            .queue(cursor::MoveTo(0, row))?
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .queue(cursor::MoveTo(
+                (width as usize).saturating_sub(label.len() + 1) as u16,
+                row,
             ))?
-            .execute(cursor::MoveToColumn(1))?
-            .execute(style::Print(""))?
-            .execute(cursor::MoveRight(2))?
-            .execute(style::PrintStyledContent(
-                format!(
-                    "{:<space$}",
-                    self.search_buffer,
-                    space = (terminal::size()?.0 as usize - 4)
-                        * if self.search_buffer.len() == 0 { 0 } else { 1 }
+            .queue(style::PrintStyledContent(
+                label.with(self.theme.muted).italic(),
+            ))?
+            .queue(cursor::MoveTo(1, row))?
+            .queue(style::Print("󰌷"))?
+            .queue(cursor::MoveTo(4, row))?
+            .queue(style::PrintStyledContent(
+                self.link_prompt
+                    .text()
+                    .to_string()
+                    .with(self.theme.accent)
+                    .bold(),
+            ))?
+            .queue(cursor::MoveTo(
+                4 + self.link_prompt.cursor_column() as u16,
+                row,
+            ))?
+            .flush()
+    }
+
+    /// Queues the search filter on the bottom line, with the matcher in use
+    /// while it is being typed.
+    fn redraw_search_prompt(&self) -> Result<()> {
+        let (width, row) = terminal::size()?;
+        let row = row - 1;
+
+        stdout()
+            .queue(cursor::MoveTo(0, row))?
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+
+        if self.mode == Mode::Search {
+            let label = self.search.label();
+            let invalid = Matcher::new(&self.search, self.search_prompt.text()).is_err();
+
+            stdout()
+                .queue(cursor::MoveTo(
+                    (width as usize).saturating_sub(label.len() + 1) as u16,
+                    row,
+                ))?
+                .queue(style::PrintStyledContent(
+                    label
+                        .with(if invalid {
+                            self.theme.danger
+                        } else {
+                            self.theme.muted
+                        })
+                        .italic(),
+                ))?;
+        }
+
+        stdout()
+            .queue(cursor::MoveTo(1, row))?
+            .queue(style::Print(""))?
+            .queue(cursor::MoveTo(4, row))?
+            .queue(style::PrintStyledContent(
+                text::fit(
+                    self.search_prompt.text(),
+                    (width as usize - 4) * if self.search_prompt.is_empty() { 0 } else { 1 },
                 )
-                .clone()
                 .underlined(),
             ))?
-            .execute(cursor::MoveTo(
-                previous_cursor_position.0,
-                previous_cursor_position.1,
-            ))?;
-
-        Ok(())
+            .queue(cursor::MoveTo(
+                4 + self.search_prompt.cursor_column() as u16,
+                row,
+            ))?
+            .flush()
     }
 
     fn draw_help(&self) -> Result<()> {
@@ -311,63 +976,110 @@ impl NFM {
             .queue(style::Print("Help"))?
             .queue(cursor::MoveTo(window.position.0 + 2, window.position.1 + 3))?;
 
-        let help_entries = vec![
-            format!(
-                "󰈆 Esc:{:>padding$}",
-                "Quit",
-                padding = window.size.0 as usize - 9
-            ),
-            format!(
-                "󰹺 Up/Down:{:>padding$}",
+        let help_entries = [
+            ("󰈆", &[Action::Close][..], "Quit"),
+            (
+                "󰹺",
+                &[Action::MoveUp, Action::MoveDown],
                 "Navigate between entries",
-                padding = window.size.0 as usize - 13
             ),
-            format!(
-                " Ctrl-Up/Ctrl-Down:{:>padding$}",
+            (
+                "",
+                &[Action::ScrollUp, Action::ScrollDown],
                 "Navigate between entries and scroll",
-                padding = window.size.0 as usize - 23
-            ),
-            format!(
-                "󰿄 Enter:{:>padding$}",
-                "Open",
-                padding = window.size.0 as usize - 11
             ),
-            format!(
-                "󰌍 Backspace:{:>padding$}",
-                "Go back",
-                padding = window.size.0 as usize - 15
+            ("󰿄", &[Action::Open], "Open"),
+            ("󰌍", &[Action::Back], "Go back"),
+            ("󰨿", &[Action::Home, Action::End], "Go to first/last entry"),
+            ("󰘓", &[Action::ToggleHidden], "Toggle hidden entries"),
+            ("󰈈", &[Action::TogglePreview], "Toggle preview pane"),
+            (
+                "󰕰",
+                &[Action::ToggleColumns],
+                "Toggle parent and child columns",
             ),
-            format!(
-                "󰨿 Home/End:{:>padding$}",
-                "Go to first/last entry",
-                padding = window.size.0 as usize - 14
+            ("󰈙", &[Action::ToggleDetails], "Toggle long listing"),
+            (
+                "",
+                &[Action::ToggleFollowLinks],
+                "Toggle link icons/icons of link targets",
             ),
-            format!(
-                "󰘓 h:{:>padding$}",
-                "Toggle hidden entries",
-                padding = window.size.0 as usize - 7
+            ("󰁕", &[Action::JumpToTarget], "Go to the target of a link"),
+            ("󰒺", &[Action::CycleSort], "Cycle sort key"),
+            (
+                "󰒿",
+                &[Action::ReverseSort, Action::ToggleDirectoriesFirst],
+                "Reverse sort/toggle directories first",
             ),
-            format!(
-                "󰑕 r:{:>padding$}",
-                "Rename entry",
-                padding = window.size.0 as usize - 7
+            (
+                "󰯌",
+                &[Action::ToggleDual],
+                "Toggle two listings side by side",
             ),
-            format!(
-                "󰆴 d:{:>padding$}",
-                "Delete entry",
-                padding = window.size.0 as usize - 7
+            ("󰁔", &[Action::SwitchPane], "Use the other listing"),
+            ("󰓡", &[Action::SwapPanes], "Swap the listings"),
+            (
+                "󰑓",
+                &[Action::SyncPane],
+                "Show this directory in the other listing",
             ),
-            format!(
-                " a:{:>padding$}",
+            ("󰓩", &[Action::NewTab], "Open a new tab"),
+            ("󰅖", &[Action::CloseTab], "Close the tab"),
+            ("󰒭", &[Action::NextTab, Action::PreviousTab], "Switch tabs"),
+            ("󰑕", &[Action::Rename], "Rename entry"),
+            ("󰑕", &[Action::BulkRename], "Rename entries in $EDITOR"),
+            ("󰆴", &[Action::Remove], "Move entry to trash"),
+            ("󰩹", &[Action::Delete], "Delete entry permanently"),
+            ("󰩺", &[Action::ShowTrash], "Browse the trash"),
+            (
+                "",
+                &[Action::Add],
                 "Add entry (name ending with '/' is a directory)",
-                padding = window.size.0 as usize - 7
             ),
-            format!(
-                "󰞋 ?:{:>padding$}",
-                "Toggle this help menu",
-                padding = window.size.0 as usize - 7
+            ("󰌷", &[Action::Link], "Link entries (C-t picks the kind)"),
+            (
+                "󰌾",
+                &[Action::EditPermissions],
+                "Change permissions and owner",
+            ),
+            ("", &[Action::Search], "Search entries"),
+            ("󰥨", &[Action::Find], "Find entries below this directory"),
+            (
+                "󰱼",
+                &[Action::Grep],
+                "Search file contents below this directory",
+            ),
+            ("󰄲", &[Action::ToggleMark], "Toggle mark on entry"),
+            (
+                "󰒆",
+                &[Action::MarkAll, Action::InvertMarks],
+                "Mark all/invert marks",
             ),
-        ];
+            ("󰛔", &[Action::MarkGlob], "Mark entries matching a glob"),
+            ("󰆏", &[Action::Copy, Action::Cut], "Copy/cut entries"),
+            ("󰆒", &[Action::Paste], "Paste entries here"),
+            ("󰕌", &[Action::Undo, Action::Redo], "Undo/redo last change"),
+            ("󰞋", &[Action::ToggleHelp], "Toggle this help menu"),
+        ]
+        .iter()
+        .map(|(icon, actions, description)| {
+            let keys = actions
+                .iter()
+                .flat_map(|action| self.keymap.sequences(&Mode::Normal, action))
+                .map(format_sequence)
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let label = format!("{} {}:", icon, keys);
+
+            format!(
+                "{}{:>padding$}",
+                label,
+                description,
+                padding = (window.size.0 as usize - 3).saturating_sub(text::width(&label))
+            )
+        })
+        .collect::<Vec<_>>();
 
         for entry in help_entries.iter() {
             stdout()
@@ -379,445 +1091,1738 @@ impl NFM {
         stdout().flush()
     }
 
-    fn handle_actions(&mut self) -> Result<()> {
-        for action in self.actions.iter() {
-            match &self.mode {
-                Mode::Normal => match action {
-                    Action::Close => self.should_close = true,
-                    Action::Redraw => self.entries = self.draw()?,
+    /// Moves the selection to the entry called `name`, scrolling it into view.
+    fn select(&mut self, name: &OsString) -> Result<()> {
+        let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.base.file_name() == *name)
+        else {
+            return Ok(());
+        };
 
-                    Action::ScrollUp => {
-                        self.scroll = self.scroll.saturating_sub(1);
+        let visible = terminal::size()?.1.saturating_sub(4).max(1);
 
-                        if self.selection + self.scroll > terminal::size()?.1 - 4 {
-                            self.selection -= 1;
-                        }
+        self.selection = index as u16;
 
-                        self.draw()?;
-                    }
+        if self.selection < self.scroll {
+            self.scroll = self.selection;
+        } else if self.selection - self.scroll >= visible {
+            self.scroll = self.selection - visible + 1;
+        }
 
-                    Action::ScrollDown => {
-                        self.scroll += 1;
+        Ok(())
+    }
 
-                        if self.selection.saturating_sub(self.scroll) < terminal::size()?.1 - 3 {
-                            self.selection =
-                                (self.selection + 1).min(self.entries.len() as u16 - 1);
-                        }
+    /// Starts renaming the selected entry inline.
+    fn begin_rename(&mut self) -> Result<()> {
+        self.mode = Mode::Rename;
 
-                        self.draw()?;
-                    }
+        self.rename_prompt.set(name::display(
+            &self.entries[self.selection as usize].base.file_name(),
+        ));
 
-                    Action::MoveUp => {
-                        if self.selection.saturating_sub(self.scroll) == 0 {
-                            self.scroll = self.scroll.saturating_sub(1);
-                        }
+        stdout().execute(cursor::Show)?;
+        self.redraw_rename_prompt()
+    }
 
-                        self.selection = self.selection.saturating_sub(1);
+    /// Continues a bulk rename with the next marked entry, if any is left.
+    fn rename_next(&mut self) -> Result<()> {
+        while let Some(name) = self.rename_queue.pop() {
+            self.entries = self.draw()?;
 
-                        self.draw()?;
-                    }
+            if self.entries.iter().any(|e| e.base.file_name() == name) {
+                self.select(&name)?;
+                self.entries = self.draw()?;
+                return self.begin_rename();
+            }
+        }
 
-                    Action::MoveDown => {
-                        self.selection =
-                            (self.selection + 1).min(self.entries.len().saturating_sub(1) as u16);
+        Ok(())
+    }
 
-                        if self.selection.saturating_sub(self.scroll) >= terminal::size()?.1 - 4 {
-                            self.scroll += 1;
-                        }
+    /// The marked entries in listing order, or the selected one if nothing is
+    /// marked.
+    fn targets(&self) -> Vec<PathBuf> {
+        if self.marked.is_empty() {
+            return self
+                .entries
+                .get(self.selection as usize)
+                .map(|entry| vec![entry.base.path()])
+                .unwrap_or_default();
+        }
 
-                        self.draw()?;
-                    }
+        self.entries
+            .iter()
+            .filter(|entry| self.marked.contains(&entry.base.file_name()))
+            .map(|entry| entry.base.path())
+            .collect()
+    }
 
-                    Action::Home => {
-                        self.selection = 0;
-                        self.scroll = 0;
-                        self.draw()?;
-                    }
+    /// Reports where a paste stopped: either on a conflict the user has to
+    /// settle, or at its end.
+    fn continue_paste(&mut self, conflict: Option<PathBuf>) -> Result<()> {
+        self.entries = self.draw()?;
 
-                    Action::End => {
-                        self.selection = self.entries.len() as u16 - 1;
+        let Some(conflict) = conflict else {
+            if let Some(paste) = self.paste.take() {
+                if paste.kind == RegisterKind::Cut {
+                    self.register = None;
+                }
 
-                        while self.selection.saturating_sub(self.scroll) >= terminal::size()?.1 - 3
-                        {
-                            self.scroll += 1;
+                self.journal.record(paste.operations);
+            }
+
+            self.mode = Mode::Normal;
+            self.entries = self.draw()?;
+            return Ok(());
+        };
+
+        self.mode = Mode::Conflict;
+
+        stdout()
+            .execute(cursor::MoveTo(1, terminal::size()?.1 - 1))?
+            .execute(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .execute(style::Print(
+                format!(
+                    "{} exists: [s]kip [o]verwrite [r]ename, uppercase for all",
+                    name::display(conflict.file_name().unwrap_or_default())
+                )
+                .with(self.theme.danger)
+                .italic(),
+            ))?;
+
+        Ok(())
+    }
+
+    /// Asks for confirmation before moving the marked or selected entries to
+    /// the trash, or deleting them for good if `permanent` is set.
+    fn confirm_removal(&mut self, permanent: bool) -> Result<()> {
+        self.mode = if permanent {
+            Mode::Delete
+        } else {
+            Mode::Remove
+        };
+
+        let prompt = if permanent {
+            "Confirm permanent deletion (Enter/Esc)"
+        } else {
+            "Confirm removal to trash (Enter/Esc)"
+        };
+
+        if !self.marked.is_empty() {
+            self.entries = self.draw()?;
+
+            stdout()
+                .execute(cursor::MoveTo(1, terminal::size()?.1 - 1))?
+                .execute(terminal::Clear(terminal::ClearType::CurrentLine))?
+                .execute(style::Print(
+                    format!("{} marked entries: {}", self.marked.len(), prompt)
+                        .with(self.theme.danger)
+                        .italic(),
+                ))?;
+
+            return Ok(());
+        }
+
+        let (column, width) = self.panes()?.list;
+        let entry = &mut self.entries[self.selection as usize];
+        entry.mark_for_removal();
+
+        stdout().queue(cursor::MoveTo(column, self.selection - self.scroll + 2))?;
+        entry.draw(
+            self.selection,
+            self.selection as usize,
+            width as usize,
+            &self.theme,
+        )?;
+
+        stdout()
+            .execute(cursor::MoveTo(column + 4, self.selection - self.scroll + 2))?
+            .execute(style::Print(
+                name::display(&self.entries[self.selection as usize].base.file_name())
+                    .with(self.theme.danger)
+                    .on(self.theme.selection)
+                    .bold()
+                    .italic(),
+            ))?
+            .execute(style::Print(
+                format!("  {}", prompt)
+                    .with(if permanent {
+                        self.theme.danger
+                    } else {
+                        self.theme.accent
+                    })
+                    .on(self.theme.selection)
+                    .italic(),
+            ))?;
+
+        Ok(())
+    }
+
+    fn draw_trash(&self) -> Result<()> {
+        let terminal_size = terminal::size()?;
+        let visible = terminal_size.1.saturating_sub(4).max(1);
+        let scroll = self.trash_selection.saturating_sub(visible - 1);
+
+        stdout()
+            .queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::PrintStyledContent(
+                format!(
+                    " {}{:>padding$}",
+                    "Trash".with(self.theme.accent).italic(),
+                    "Restore or purge items, Esc to go back",
+                    padding = terminal_size.0 as usize - 7,
+                )
+                .stylize(),
+            ))?
+            .queue(cursor::MoveToNextLine(2))?;
+
+        if self.trash_items.is_empty() {
+            stdout().queue(style::PrintStyledContent(
+                " Empty".with(self.theme.muted).italic(),
+            ))?;
+        }
+
+        for (index, item) in self
+            .trash_items
+            .iter()
+            .enumerate()
+            .skip(scroll as usize)
+            .take(visible as usize)
+        {
+            let line = format!(
+                " {}  {}",
+                item.deleted.replace('T', " "),
+                name::display(item.original.as_os_str())
+            );
+
+            stdout()
+                .queue(style::PrintStyledContent(
+                    if index == self.trash_selection as usize {
+                        format!("{:<width$}", line, width = terminal_size.0 as usize)
+                            .on(self.theme.selection)
+                            .bold()
+                    } else {
+                        line.stylize()
+                    },
+                ))?
+                .queue(cursor::MoveToNextLine(1))?;
+        }
+
+        stdout().flush()
+    }
+
+    /// Brings the finder's matches up to date with the query and the paths
+    /// found so far.
+    fn update_matches(&mut self) {
+        if let Some(finder) = &self.finder {
+            self.matches
+                .update(&finder.items, &self.search, self.results_prompt.text());
+        }
+    }
+
+    /// How many results the finder or content search has to choose from.
+    fn results_len(&mut self) -> usize {
+        match self.mode {
+            Mode::Find => {
+                self.update_matches();
+                self.matches.found.len()
+            }
+            _ => self.grep.as_ref().map_or(0, |grep| grep.items.len()),
+        }
+    }
+
+    fn draw_results(&mut self) -> Result<()> {
+        let searching = |done: bool| if done { "" } else { ", searching" };
+
+        self.update_matches();
+
+        let list = if self.mode == Mode::Find {
+            let Some(finder) = &self.finder else {
+                return Ok(());
+            };
+
+            let found = &self.matches.found;
+            let visible = ResultList::visible(self.results_selection, found.len())?;
+
+            ResultList {
+                title: format!("Find in: {}", name::display(finder.root.as_os_str())),
+                hint: format!(
+                    "{} of {}{}",
+                    found.len(),
+                    finder.items.len(),
+                    searching(finder.done)
+                ),
+                rows: found[visible.clone()]
+                    .iter()
+                    .map(|(index, _, highlights)| Row {
+                        prefix: String::new(),
+                        text: name::display(finder.items[*index].as_os_str()),
+                        highlights: highlights.clone(),
+                    })
+                    .collect(),
+                first: visible.start,
+                selection: self.results_selection,
+                empty: if finder.done {
+                    "Nothing found"
+                } else {
+                    "Searching"
+                },
+                query: &self.results_prompt,
+                label: self.search.label(),
+                invalid: Matcher::new(&self.search, self.results_prompt.text()).is_err(),
+            }
+        } else {
+            let (hint, empty) = match &self.grep {
+                Some(grep) if self.grep_query == self.results_prompt.text() => (
+                    format!("{} matches{}", grep.items.len(), searching(grep.done)),
+                    if grep.done {
+                        "Nothing found"
+                    } else {
+                        "Searching"
+                    },
+                ),
+                _ => (
+                    "Enter to search".to_string(),
+                    "Type what to look for and press Enter",
+                ),
+            };
+
+            let items = self.grep.as_ref().map_or(&[][..], |grep| &grep.items);
+            let visible = ResultList::visible(self.results_selection, items.len())?;
+
+            ResultList {
+                title: format!("Grep in: {}", name::display(self.path.as_os_str())),
+                hint,
+                rows: items[visible.clone()]
+                    .iter()
+                    .map(|found| Row {
+                        prefix: format!(
+                            "{}:{}: ",
+                            name::display(found.path.as_os_str()),
+                            found.line
+                        ),
+                        text: found.snippet.clone(),
+                        highlights: found.highlights.clone(),
+                    })
+                    .collect(),
+                first: visible.start,
+                selection: self.results_selection,
+                empty,
+                query: &self.results_prompt,
+                label: if self.search.matcher == MatcherKind::Regex {
+                    self.search.label()
+                } else {
+                    Search {
+                        matcher: MatcherKind::Substring,
+                        ..self.search
+                    }
+                    .label()
+                },
+                invalid: grep::pattern(&self.search, self.results_prompt.text()).is_err(),
+            }
+        };
+
+        list.draw(&self.theme)
+    }
+
+    fn draw_rename_plan(&self) -> Result<()> {
+        let Some(plan) = &self.rename_plan else {
+            return Ok(());
+        };
+
+        let terminal_size = terminal::size()?;
+        let visible = terminal_size.1.saturating_sub(4).max(1) as usize;
+        let title = format!("Rename {} entries", plan.renames.len());
+
+        stdout()
+            .queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::PrintStyledContent(
+                format!(
+                    " {}{:>padding$}",
+                    title.clone().with(self.theme.accent).italic(),
+                    "Enter to apply, Esc to cancel",
+                    padding = (terminal_size.0 as usize).saturating_sub(title.len() + 2),
+                )
+                .stylize(),
+            ))?
+            .queue(cursor::MoveToNextLine(2))?;
+
+        for (index, (old, new)) in plan.renames.iter().enumerate() {
+            if index + 1 == visible && plan.renames.len() > visible {
+                stdout().queue(style::PrintStyledContent(
+                    format!(" and {} more", plan.renames.len() - index)
+                        .with(self.theme.muted)
+                        .italic(),
+                ))?;
+                break;
+            }
+
+            stdout()
+                .queue(style::Print(format!(
+                    " {} {} {}",
+                    name::display(old),
+                    "->".with(self.theme.accent),
+                    name::display(new).bold()
+                )))?
+                .queue(cursor::MoveToNextLine(1))?;
+        }
+
+        stdout().flush()
+    }
+
+    /// Runs `launch` for an entry. Foreground commands get the terminal to
+    /// themselves for as long as they run, detached ones are left running.
+    fn launch(&mut self, mut launch: Launch) -> Result<()> {
+        launch.command.current_dir(&self.path);
+
+        if launch.detach {
+            self.detached.push(launch.command.spawn()?);
+            return Ok(());
+        }
+
+        self.deinitialize()?;
+        let status = launch.command.status();
+        self.initialize()?;
+
+        status.map(|_| ())
+    }
+
+    /// Reports that `action`, taken in `mode`, failed with `error`, and puts
+    /// nfm back in a state it can carry on from.
+    fn report(&mut self, mode: &Mode, action: &Action, error: Error) -> Result<()> {
+        stdout().execute(cursor::Hide)?;
+
+        // Whatever was pasted before the failure can still be undone.
+        if let Some(paste) = self.paste.take() {
+            self.journal.record(paste.operations);
+        }
+
+        // The directory in view may be gone or unreadable, in which case the
+        // closest one that can be listed is shown instead. The same goes for
+        // the other listing and the tabs in the background.
+        let path = listable(&self.path);
+
+        if path != self.path {
+            self.set_path(path);
+            self.selection = 0;
+            self.scroll = 0;
+        }
+
+        // The slot of the tab in view only holds an empty placeholder, which
+        // is left as it is.
+        for tab in self.tabs.iter_mut().chain([&mut self.other]) {
+            let path = listable(&tab.path);
+
+            if path != tab.path {
+                *tab = Tab::new(path, tab.sort);
+            }
+        }
+
+        let message = match operation(mode, action) {
+            Some(operation) => format!("{} failed: {}", operation, error),
+            None => error.to_string(),
+        };
+
+        self.show_message(message)
+    }
+
+    fn show_message(&mut self, message: String) -> Result<()> {
+        self.mode = Mode::Message;
+        self.message = message;
+        self.entries = self.draw()?;
+        self.draw_message()
+    }
+
+    fn draw_message(&self) -> Result<()> {
+        let terminal_size = terminal::size()?;
+        let window_size = (terminal_size.0 * 2 / 3, terminal_size.1 / 2);
+        let window = Window::new(
+            (
+                terminal_size.0 / 2 - window_size.0 / 2,
+                terminal_size.1 / 2 - window_size.1 / 2,
+            ),
+            window_size,
+        );
+        window.draw()?;
+
+        stdout()
+            .queue(cursor::MoveTo(
+                window.position.0 + window.size.0 / 2 - 2,
+                window.position.1 + 1,
+            ))?
+            .queue(style::PrintStyledContent("Error".with(self.theme.danger)))?
+            .queue(cursor::MoveTo(window.position.0 + 2, window.position.1 + 3))?;
+
+        let width = window.size.0.saturating_sub(3) as usize;
+        let lines = self
+            .message
+            .lines()
+            .flat_map(|line| text::wrap(line, width.max(1)))
+            .take(window.size.1.saturating_sub(3) as usize);
+
+        for line in lines {
+            stdout()
+                .queue(style::Print(line))?
+                .queue(cursor::MoveToNextLine(1))?
+                .queue(cursor::MoveToColumn(window.position.0 + 2))?;
+        }
+
+        stdout().flush()
+    }
+
+    fn handle_actions(&mut self) -> Result<()> {
+        for action in mem::take(&mut self.actions).iter() {
+            let mode = self.mode.clone();
+
+            match self.handle_action(action) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(error) => {
+                    self.report(&mode, action, error)?;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Carries out `action`, returning whether the actions queued after it
+    /// should still be carried out.
+    fn handle_action(&mut self, action: &Action) -> Result<bool> {
+        match &self.mode {
+            Mode::Normal => match action {
+                Action::Close => self.should_close = true,
+                Action::Redraw => self.entries = self.draw()?,
+
+                Action::ScrollUp => {
+                    self.scroll = self.scroll.saturating_sub(1);
+
+                    if self.selection + self.scroll > terminal::size()?.1 - 4 {
+                        self.selection -= 1;
+                    }
+
+                    self.draw()?;
+                }
+
+                Action::ScrollDown => {
+                    self.scroll += 1;
+
+                    if self.selection.saturating_sub(self.scroll) < terminal::size()?.1 - 3 {
+                        self.selection = (self.selection + 1).min(self.entries.len() as u16 - 1);
+                    }
+
+                    self.draw()?;
+                }
+
+                Action::MoveUp => {
+                    if self.selection.saturating_sub(self.scroll) == 0 {
+                        self.scroll = self.scroll.saturating_sub(1);
+                    }
+
+                    self.selection = self.selection.saturating_sub(1);
+
+                    self.draw()?;
+                }
+
+                Action::MoveDown => {
+                    self.selection =
+                        (self.selection + 1).min(self.entries.len().saturating_sub(1) as u16);
+
+                    if self.selection.saturating_sub(self.scroll) >= terminal::size()?.1 - 4 {
+                        self.scroll += 1;
+                    }
+
+                    self.draw()?;
+                }
+
+                Action::Home => {
+                    self.selection = 0;
+                    self.scroll = 0;
+                    self.draw()?;
+                }
+
+                Action::End => {
+                    self.selection = self.entries.len() as u16 - 1;
+
+                    while self.selection.saturating_sub(self.scroll) >= terminal::size()?.1 - 3 {
+                        self.scroll += 1;
+                    }
+
+                    self.draw()?;
+                }
+
+                Action::NewTab => {
+                    self.store_tab();
+                    self.tabs
+                        .insert(self.tab + 1, Tab::new(self.path.clone(), self.sort));
+                    self.tab += 1;
+                    self.load_tab();
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::CloseTab => {
+                    if self.tabs.len() == 1 {
+                        return Ok(false);
+                    }
+
+                    self.tabs.remove(self.tab);
+                    self.tab = self.tab.min(self.tabs.len() - 1);
+                    self.load_tab();
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::NextTab | Action::PreviousTab => {
+                    let count = self.tabs.len();
+
+                    self.store_tab();
+                    self.tab = if *action == Action::NextTab {
+                        (self.tab + 1) % count
+                    } else {
+                        (self.tab + count - 1) % count
+                    };
+                    self.load_tab();
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::CycleSort | Action::ReverseSort | Action::ToggleDirectoriesFirst => {
+                    match action {
+                        Action::CycleSort => self.sort.by = self.sort.by.next(),
+                        Action::ReverseSort => self.sort.reverse = !self.sort.reverse,
+                        _ => self.sort.directories_first = !self.sort.directories_first,
+                    }
+
+                    if self.default_sort.remember {
+                        self.remembered.set(&self.path, self.sort)?;
+                    }
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::ToggleFollowLinks => {
+                    self.follow_links = !self.follow_links;
+                    self.entries = self.draw()?;
+                }
+
+                Action::JumpToTarget => {
+                    let Some(entry) = self.entries.get(self.selection as usize) else {
+                        return Ok(false);
+                    };
+
+                    if !entry.base.file_type()?.is_symlink() {
+                        return Ok(false);
+                    }
+
+                    let target = match canonicalize(entry.base.path()) {
+                        Ok(target) => target,
+                        Err(error) => {
+                            self.show_message(format!(
+                                "Cannot follow {}: {}",
+                                name::display(&entry.base.file_name()),
+                                error
+                            ))?;
+                            return Ok(false);
                         }
+                    };
+
+                    let (Some(parent), Some(name)) = (target.parent(), target.file_name()) else {
+                        return Ok(false);
+                    };
+
+                    self.set_path(parent.to_path_buf());
+                    self.search_prompt.clear();
+                    self.selection = 0;
+                    self.scroll = 0;
+
+                    self.entries = self.draw()?;
+                    self.select(&name.to_os_string())?;
+                    self.entries = self.draw()?;
+                }
+
+                Action::ToggleDetails => {
+                    self.details.show = !self.details.show;
+                    self.entries = self.draw()?;
+                }
+
+                Action::SwitchPane => {
+                    if self.layout != Layout::Dual {
+                        return Ok(false);
+                    }
+
+                    let state = self.take_state();
+                    let other = mem::replace(&mut self.other, state);
+                    self.put_state(other);
+                    self.right = !self.right;
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::SwapPanes => {
+                    self.right = !self.right;
+                    self.entries = self.draw()?;
+                }
+
+                Action::SyncPane => {
+                    self.other = Tab::new(self.path.clone(), self.sort);
+                    self.entries = self.draw()?;
+                }
+
+                Action::TogglePreview | Action::ToggleColumns | Action::ToggleDual => {
+                    let layout = match action {
+                        Action::TogglePreview => Layout::Preview,
+                        Action::ToggleColumns => Layout::Columns,
+                        _ => Layout::Dual,
+                    };
+
+                    self.layout = if self.layout == layout {
+                        Layout::Single
+                    } else {
+                        layout
+                    };
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::ToggleHidden => {
+                    self.show_hidden = !self.show_hidden;
+                    self.scroll = 0;
+                    self.entries = self.draw()?;
+                    self.selection = self
+                        .selection
+                        .min(self.entries.len().saturating_sub(1) as u16);
+                    self.entries = self.draw()?;
+                }
+
+                Action::Rename => {
+                    if !self.marked.is_empty() {
+                        self.rename_queue = self
+                            .entries
+                            .iter()
+                            .rev()
+                            .map(|entry| entry.base.file_name())
+                            .filter(|name| self.marked.contains(name))
+                            .collect();
+
+                        self.rename_next()?;
+                        return Ok(true);
+                    }
+
+                    if self.selection >= self.entries.len() as u16 {
+                        return Ok(false);
+                    }
+
+                    self.begin_rename()?;
+                }
+
+                Action::Remove | Action::Delete => {
+                    if self.marked.is_empty() && self.selection >= self.entries.len() as u16 {
+                        return Ok(false);
+                    }
+
+                    self.confirm_removal(*action == Action::Delete)?;
+                }
+
+                Action::ShowTrash => {
+                    self.mode = Mode::Trash;
+                    self.trash_items = Trash::home()?.items()?;
+                    self.trash_selection = 0;
+                    self.draw_trash()?;
+                }
+
+                Action::Add => {
+                    self.mode = Mode::Add;
+
+                    stdout().execute(cursor::Show)?;
+                    self.redraw_add_prompt()?;
+                }
+
+                Action::Open => {
+                    if self.selection >= self.entries.len() as u16 {
+                        return Ok(false);
+                    }
+
+                    let target = self
+                        .entries
+                        .get(self.selection as usize)
+                        .unwrap()
+                        .base
+                        .path();
+
+                    if target.is_dir() {
+                        self.set_path(target);
+                    } else {
+                        self.launch(self.opener.resolve(&target, None))?;
+                    }
+
+                    self.search_prompt.clear();
+                    self.scroll = 0;
+                    self.entries = self.draw()?;
+                    self.selection = self
+                        .selection
+                        .min(self.entries.len().saturating_sub(1) as u16);
+                    self.entries = self.draw()?;
+                }
+
+                Action::Back => {
+                    if let Some(parent) = self.path.parent() {
+                        self.set_path(parent.to_path_buf());
+                    }
+
+                    self.scroll = 0;
+
+                    self.entries = self.draw()?;
+                    self.selection = self
+                        .selection
+                        .min(self.entries.len().saturating_sub(1) as u16);
+                    self.entries = self.draw()?;
+                }
+
+                Action::Find | Action::Grep => {
+                    if *action == Action::Find {
+                        self.mode = Mode::Find;
+                        self.matches = Matches::default();
+                        self.finder = Some(Walk::spawn(
+                            self.path.clone(),
+                            self.show_hidden,
+                            |_, relative| vec![relative.to_path_buf()],
+                        ));
+                    } else {
+                        self.mode = Mode::Grep;
+                        self.grep_query.clear();
+                    }
+
+                    self.results_prompt.clear();
+                    self.results_selection = 0;
+
+                    stdout().execute(cursor::Show)?;
+                    self.draw_results()?;
+                }
+
+                Action::Search => {
+                    self.mode = Mode::Search;
+                    self.search_prompt.clear();
+
+                    stdout().execute(cursor::Show)?;
+                    self.redraw_search_prompt()?;
+                }
+
+                Action::ToggleMark => {
+                    let Some(entry) = self.entries.get(self.selection as usize) else {
+                        return Ok(false);
+                    };
+
+                    let name = entry.base.file_name();
+
+                    if !self.marked.remove(&name) {
+                        self.marked.insert(name);
+                    }
+
+                    self.selection =
+                        (self.selection + 1).min(self.entries.len().saturating_sub(1) as u16);
+
+                    if self.selection.saturating_sub(self.scroll) >= terminal::size()?.1 - 4 {
+                        self.scroll += 1;
+                    }
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::MarkAll => {
+                    self.marked
+                        .extend(self.entries.iter().map(|entry| entry.base.file_name()));
+                    self.entries = self.draw()?;
+                }
+
+                Action::InvertMarks => {
+                    for entry in self.entries.iter() {
+                        let name = entry.base.file_name();
+
+                        if !self.marked.remove(&name) {
+                            self.marked.insert(name);
+                        }
+                    }
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::Link => {
+                    if self.targets().is_empty() {
+                        return Ok(false);
+                    }
+
+                    // Links go to the other listing or the next tab when
+                    // there is one, and next to their targets otherwise.
+                    let destination = if self.layout == Layout::Dual {
+                        &self.other.path
+                    } else if self.tabs.len() > 1 {
+                        &self.tabs[(self.tab + 1) % self.tabs.len()].path
+                    } else {
+                        &self.path
+                    };
+
+                    let mut buffer = name::display(destination.as_os_str());
+
+                    if !buffer.ends_with('/') {
+                        buffer.push('/');
+                    }
+
+                    self.mode = Mode::Link;
+                    self.link_prompt.set(buffer);
+
+                    stdout().execute(cursor::Show)?;
+                    self.redraw_link_prompt()?;
+                }
+
+                Action::EditPermissions => {
+                    let targets = self.targets();
+
+                    if targets.is_empty() {
+                        return Ok(false);
+                    }
+
+                    match PermissionEditor::new(targets) {
+                        Ok(editor) => {
+                            self.mode = Mode::Permissions;
+                            editor.draw(&self.theme)?;
+                            self.permissions = Some(editor);
+                        }
+                        Err(error) => self.show_message(error.to_string())?,
+                    }
+                }
+
+                Action::MarkGlob => {
+                    self.mode = Mode::Mark;
+                    self.mark_prompt.clear();
+
+                    stdout().execute(cursor::Show)?;
+                    self.redraw_mark_prompt()?;
+                }
+
+                Action::Copy | Action::Cut => {
+                    let paths = self.targets();
+
+                    if paths.is_empty() {
+                        return Ok(false);
+                    }
+
+                    let register = Register {
+                        kind: if *action == Action::Copy {
+                            RegisterKind::Copy
+                        } else {
+                            RegisterKind::Cut
+                        },
+                        paths,
+                    };
+
+                    self.marked.clear();
+
+                    // With two listings the other one is where entries go,
+                    // so they are pasted there right away.
+                    if self.layout == Layout::Dual {
+                        let mut paste = Paste::new(&register, self.other.path.clone());
+                        let conflict = paste.run();
+                        self.paste = Some(paste);
+                        self.continue_paste(conflict?)?;
+                        return Ok(false);
+                    }
+
+                    self.register = Some(register);
+                    self.entries = self.draw()?;
+                }
+
+                Action::Paste => {
+                    let Some(register) = &self.register else {
+                        return Ok(false);
+                    };
 
-                        self.draw()?;
-                    }
+                    let mut paste = Paste::new(register, self.path.clone());
+                    let conflict = paste.run();
+                    self.paste = Some(paste);
+                    self.continue_paste(conflict?)?;
+                }
 
-                    Action::ToggleHidden => {
-                        self.show_hidden = !self.show_hidden;
-                        self.scroll = 0;
-                        self.entries = self.draw()?;
-                        self.selection = self
-                            .selection
-                            .min(self.entries.len().saturating_sub(1) as u16);
-                        self.entries = self.draw()?;
+                Action::BulkRename => {
+                    let names = self
+                        .entries
+                        .iter()
+                        .map(|entry| entry.base.file_name())
+                        .filter(|name| self.marked.is_empty() || self.marked.contains(name))
+                        .filter(|name| rename_plan::fits_listing(name))
+                        .collect::<Vec<_>>();
+
+                    if names.is_empty() {
+                        return Ok(false);
                     }
 
-                    Action::Rename => {
-                        if self.selection >= self.entries.len() as u16 {
-                            break;
+                    let listing = Listing::create(&RenamePlan::listing(&names))?;
+                    let launched = self.launch(Launch::editor(&listing.path));
+                    let edited = read(&listing.path);
+                    drop(listing);
+                    launched?;
+
+                    match RenamePlan::new(&self.path, &names, &edited?) {
+                        Ok(plan) if plan.renames.is_empty() => {
+                            self.entries = self.draw()?;
                         }
 
-                        self.mode = Mode::Rename;
+                        Ok(plan) => {
+                            self.mode = Mode::BulkRename;
+                            self.rename_plan = Some(plan);
+                            self.draw_rename_plan()?;
+                        }
 
-                        self.rename_buffer = self
-                            .entries
-                            .get(self.selection as usize)
-                            .unwrap()
-                            .base
-                            .file_name()
-                            .into_string()
-                            .unwrap();
-
-                        stdout()
-                            .execute(cursor::MoveTo(
-                                self.entries
-                                    .get(self.selection as usize)
-                                    .unwrap()
-                                    .base
-                                    .file_name()
-                                    .len() as u16
-                                    + 4,
-                                self.selection - self.scroll + 2,
-                            ))?
-                            .execute(cursor::Show)?;
-
-                        self.redraw_rename_buffer(0)?;
-                    }
-
-                    Action::Remove => {
-                        if self.selection >= self.entries.len() as u16 {
-                            break;
+                        Err(error) => {
+                            self.show_message(format!("Nothing was renamed: {}", error))?
                         }
+                    }
+                }
 
-                        self.mode = Mode::Remove;
-
-                        stdout()
-                            .execute(cursor::MoveTo(4, self.selection - self.scroll + 2))?
-                            .execute(style::Print(
-                                self.entries
-                                    .get(self.selection as usize)
-                                    .unwrap()
-                                    .base
-                                    .file_name()
-                                    .into_string()
-                                    .unwrap()
-                                    .red()
-                                    .on_black()
-                                    .bold()
-                                    .italic(),
-                            ))?
-                            .execute(style::Print(
-                                "  Confirm removal (Enter/Esc)".blue().on_black().italic(),
-                            ))?;
+                Action::Undo | Action::Redo => {
+                    let result = if *action == Action::Undo {
+                        self.journal.undo()
+                    } else {
+                        self.journal.redo()
+                    };
+
+                    match result {
+                        Ok(true) => self.entries = self.draw()?,
+                        Ok(false) => {}
+                        Err(error) => self.show_message(format!(
+                            "Cannot {}: {}",
+                            if *action == Action::Undo {
+                                "undo"
+                            } else {
+                                "redo"
+                            },
+                            error
+                        ))?,
                     }
+                }
+
+                Action::ToggleHelp => {
+                    self.mode = Mode::Help;
+                    self.draw_help()?;
+                }
 
-                    Action::Add => {
-                        self.mode = Mode::Add;
+                _ => {}
+            },
 
-                        stdout()
-                            .execute(cursor::MoveTo(4, self.entries.len() as u16 + 2))?
-                            .execute(cursor::Show)?;
+            Mode::Rename => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.rename_queue.clear();
+                    stdout().execute(cursor::Hide)?;
+                    self.draw()?;
+                }
 
-                        self.redraw_add_buffer(0)?;
-                    }
+                Action::Redraw => self.entries = self.draw()?,
 
-                    Action::Open => {
-                        if self.selection >= self.entries.len() as u16 {
-                            break;
+                Action::Input(chord) if chord.code == event::KeyCode::Enter => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+                    self.history.record(&self.rename_prompt);
+
+                    let old_name = self.entries[self.selection as usize].base.file_name();
+
+                    // An untouched name is left alone, even one that could
+                    // not be shown as it is.
+                    if name::display(&old_name) != self.rename_prompt.text() {
+                        let new_name = name::parse(self.rename_prompt.text())?;
+                        let from = self.path.join(&old_name);
+                        let to = self.path.join(&new_name);
+
+                        // rename(2) would silently replace the entry.
+                        if to != from && symlink_metadata(&to).is_ok() {
+                            return Err(Error::new(
+                                ErrorKind::AlreadyExists,
+                                format!("{} already exists", name::display(&new_name)),
+                            ));
                         }
 
-                        let target = self.entries.get(self.selection as usize).unwrap().base.path();
+                        rename(&from, &to)?;
+                        self.journal.record(vec![Operation::rename(from, to)?]);
+
+                        if self.marked.remove(&old_name) {
+                            self.marked.insert(new_name);
+                        }
+                    }
+
+                    self.entries = self.draw()?;
+                    self.rename_next()?;
+                }
+
+                Action::Input(chord) => {
+                    self.rename_prompt.edit(*chord, &self.history);
+                    self.redraw_rename_prompt()?;
+                }
+
+                _ => {}
+            },
+
+            Mode::Remove | Mode::Delete => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.entries = self.draw()?;
+                }
 
-                        if target.is_dir() {
-                            set_current_dir(target)?;
+                Action::Remove | Action::Delete => {
+                    let trash = Trash::home()?;
+                    let mut change = Vec::new();
+                    let mut result = Ok(());
+
+                    for target in self.targets() {
+                        result = if *action == Action::Delete {
+                            transfer::remove(&target)
                         } else {
-                            Command::new("nvim").arg(target).spawn()?.wait()?;
+                            trash
+                                .put(&target)
+                                .and_then(Operation::trash)
+                                .map(|operation| change.push(operation))
+                        };
 
-                            stdout().execute(cursor::Hide)?;
+                        if result.is_err() {
+                            break;
                         }
-
-                        self.search_buffer.clear();
-                        self.scroll = 0;
-                        self.entries = self.draw()?;
-                        self.selection = self
-                            .selection
-                            .min(self.entries.len().saturating_sub(1) as u16);
-                        self.entries = self.draw()?;
                     }
 
-                    Action::Back => {
-                        set_current_dir("..")?;
+                    // Entries removed before a failure stay removed, and
+                    // those moved to the trash can be brought back.
+                    self.journal.record(change);
+                    result?;
+
+                    self.mode = Mode::Normal;
+                    self.marked.clear();
+
+                    self.entries = self.draw()?;
+                    self.selection = self
+                        .selection
+                        .min(self.entries.len().saturating_sub(1) as u16);
+                    self.entries = self.draw()?;
+                }
+
+                _ => {}
+            },
+
+            Mode::BulkRename => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.rename_plan = None;
+                    self.entries = self.draw()?;
+                }
+
+                Action::Redraw => self.draw_rename_plan()?,
 
-                        self.scroll = 0;
+                Action::BulkRename => {
+                    self.mode = Mode::Normal;
 
-                        self.entries = self.draw()?;
-                        self.selection = self
-                            .selection
-                            .min(self.entries.len().saturating_sub(1) as u16);
-                        self.entries = self.draw()?;
+                    let Some(plan) = self.rename_plan.take() else {
+                        return Ok(false);
+                    };
+
+                    let mut done = Vec::new();
+                    let result = plan.apply(&self.path, &mut done);
+                    self.journal.record(done);
+                    self.marked.clear();
+
+                    match result {
+                        Ok(()) => self.entries = self.draw()?,
+                        Err(error) => {
+                            self.show_message(format!("Renaming stopped halfway: {}", error))?
+                        }
                     }
+                }
+
+                _ => {}
+            },
+
+            Mode::Trash => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.trash_items.clear();
+                    self.entries = self.draw()?;
+                }
+
+                Action::Redraw => self.draw_trash()?,
+
+                Action::MoveUp => {
+                    self.trash_selection = self.trash_selection.saturating_sub(1);
+                    self.draw_trash()?;
+                }
+
+                Action::MoveDown => {
+                    self.trash_selection = (self.trash_selection + 1)
+                        .min(self.trash_items.len().saturating_sub(1) as u16);
+                    self.draw_trash()?;
+                }
+
+                Action::Home => {
+                    self.trash_selection = 0;
+                    self.draw_trash()?;
+                }
+
+                Action::End => {
+                    self.trash_selection = self.trash_items.len().saturating_sub(1) as u16;
+                    self.draw_trash()?;
+                }
+
+                Action::Restore => {
+                    let Some(item) = self.trash_items.get(self.trash_selection as usize) else {
+                        return Ok(false);
+                    };
+
+                    let trash = Trash::home()?;
+                    trash.restore(item)?;
+
+                    self.trash_items = trash.items()?;
+                    self.trash_selection = self
+                        .trash_selection
+                        .min(self.trash_items.len().saturating_sub(1) as u16);
+                    self.draw_trash()?;
+                }
+
+                Action::Purge => {
+                    let Some(item) = self.trash_items.get(self.trash_selection as usize) else {
+                        return Ok(false);
+                    };
+
+                    self.mode = Mode::Purge;
+
+                    stdout()
+                        .execute(cursor::MoveTo(1, terminal::size()?.1 - 1))?
+                        .execute(terminal::Clear(terminal::ClearType::CurrentLine))?
+                        .execute(style::Print(
+                            format!(
+                                "Permanently delete {} (Enter/Esc)",
+                                name::display(item.original.as_os_str())
+                            )
+                            .with(self.theme.danger)
+                            .italic(),
+                        ))?;
+                }
+
+                _ => {}
+            },
+
+            Mode::Purge => match action {
+                Action::Close => {
+                    self.mode = Mode::Trash;
+                    self.draw_trash()?;
+                }
+
+                Action::Purge => {
+                    let trash = Trash::home()?;
+                    trash.purge(&self.trash_items[self.trash_selection as usize])?;
+
+                    self.mode = Mode::Trash;
+                    self.trash_items = trash.items()?;
+                    self.trash_selection = self
+                        .trash_selection
+                        .min(self.trash_items.len().saturating_sub(1) as u16);
+                    self.draw_trash()?;
+                }
+
+                _ => {}
+            },
 
-                    Action::Search => {
-                        self.mode = Mode::Search;
-                        self.search_buffer.clear();
+            Mode::Add => match action {
+                Action::Close if self.add_prompt.close_menu() => self.entries = self.draw()?,
 
-                        stdout()
-                            .execute(cursor::MoveTo(4, terminal::size()?.1 - 1))?
-                            .execute(cursor::Show)?;
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+                    self.add_prompt.clear();
+                    self.draw()?;
+                }
+
+                Action::Add => {
+                    self.mode = Mode::Normal;
+                    self.history.record(&self.add_prompt);
+                    stdout().execute(cursor::Hide)?;
 
-                        self.redraw_search_buffer()?;
+                    let name = name::parse(&completion::expand(&self.add_prompt.take()))?;
+                    let path = self.path.join(&name);
+
+                    if symlink_metadata(&path).is_ok() {
+                        self.show_message(format!("{} already exists", name::display(&name)))?;
+                        return Ok(true);
                     }
 
-                    Action::ToggleHelp => {
-                        self.mode = Mode::Help;
-                        self.draw_help()?;
+                    let directory = name.as_bytes().ends_with(b"/");
+
+                    if directory {
+                        create_dir(&path)?;
+                    } else {
+                        File::create_new(&path)?;
                     }
 
-                    _ => {}
-                },
+                    self.journal
+                        .record(vec![Operation::create(path, directory)?]);
+
+                    self.selection = self
+                        .selection
+                        .min(self.entries.len().saturating_sub(1) as u16);
+                    self.entries = self.draw()?;
+                }
+
+                Action::Redraw => self.entries = self.draw()?,
+
+                Action::Complete => {
+                    self.add_prompt.complete(&self.path);
+                    self.redraw_path_prompt(false)?;
+                }
+
+                Action::Input(chord) => {
+                    let menu_was_shown = self.add_prompt.menu().is_some();
+                    self.add_prompt.edit(*chord, &self.history);
+                    self.redraw_path_prompt(menu_was_shown)?;
+                }
+
+                _ => {}
+            },
 
-                Mode::Rename => match action {
+            Mode::Search => {
+                match action {
                     Action::Close => {
                         self.mode = Mode::Normal;
                         stdout().execute(cursor::Hide)?;
+                        self.search_prompt.clear();
                         self.draw()?;
                     }
 
-                    Action::Input(key_code) => match key_code {
-                        event::KeyCode::Backspace => {
-                            if self.rename_buffer.is_empty() {
-                                break;
-                            }
-
-                            self.move_left()?;
+                    Action::CycleMatcher => {
+                        self.search.matcher = self.search.matcher.next();
+                    }
 
-                            self.rename_buffer
-                                .remove(cursor::position()?.0 as usize - 4);
+                    Action::ToggleSmartCase => {
+                        self.search.smart_case = !self.search.smart_case;
+                    }
 
-                            self.redraw_rename_buffer(1)?;
-                        }
+                    Action::Input(chord) if chord.code == event::KeyCode::Enter => {
+                        self.mode = Mode::Normal;
+                        stdout().execute(cursor::Hide)?;
+                        self.history.record(&self.search_prompt);
+                    }
 
-                        event::KeyCode::Enter => {
-                            self.mode = Mode::Normal;
-                            stdout().execute(cursor::Hide)?;
+                    Action::Input(chord) => {
+                        self.search_prompt.edit(*chord, &self.history);
+                    }
 
-                            let old_name = self
-                                .entries
-                                .get(self.selection as usize)
-                                .unwrap()
-                                .base
-                                .file_name()
-                                .into_string()
-                                .unwrap();
+                    _ => {}
+                }
 
-                            if old_name != self.rename_buffer {
-                                rename(old_name, self.rename_buffer.clone())?;
-                            }
+                self.scroll = 0;
+                self.entries = self.draw()?;
+                self.selection = self
+                    .selection
+                    .min(self.entries.len().saturating_sub(1) as u16);
+                self.entries = self.draw()?;
+            }
 
-                            self.entries = self.draw()?;
-                        }
+            Mode::Find | Mode::Grep => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.finder = None;
+                    self.grep = None;
 
-                        event::KeyCode::Left => {
-                            self.move_left()?;
-                        }
+                    stdout().execute(cursor::Hide)?;
+                    self.entries = self.draw()?;
+                }
 
-                        event::KeyCode::Right => {
-                            self.move_right(&self.rename_buffer)?;
-                        }
+                Action::Redraw => self.draw_results()?,
 
-                        event::KeyCode::Char(character) => {
-                            self.rename_buffer
-                                .insert(cursor::position()?.0 as usize - 4, *character);
+                Action::MoveUp => {
+                    self.results_selection = self.results_selection.saturating_sub(1);
+                    self.draw_results()?;
+                }
 
-                            self.move_right(&self.rename_buffer)?;
-                            self.redraw_rename_buffer(0)?;
-                        }
+                Action::MoveDown => {
+                    self.results_selection = (self.results_selection + 1)
+                        .min(self.results_len().saturating_sub(1) as u16);
+                    self.draw_results()?;
+                }
 
-                        _ => {}
-                    },
+                Action::Home => {
+                    self.results_selection = 0;
+                    self.draw_results()?;
+                }
 
-                    _ => {}
-                },
+                Action::End => {
+                    self.results_selection = self.results_len().saturating_sub(1) as u16;
+                    self.draw_results()?;
+                }
 
-                Mode::Remove => match action {
-                    Action::Close => {
-                        self.mode = Mode::Normal;
-                        self.draw()?;
+                Action::CycleMatcher | Action::ToggleSmartCase => {
+                    if *action == Action::CycleMatcher {
+                        self.search.matcher = self.search.matcher.next();
+                    } else {
+                        self.search.smart_case = !self.search.smart_case;
                     }
 
-                    Action::Remove => {
-                        self.mode = Mode::Normal;
+                    if self.mode == Mode::Find {
+                        self.results_selection = 0;
+                    }
 
-                        let entry = self.entries.get(self.selection as usize).unwrap();
-                        let entry_name = entry.base.file_name().into_string().unwrap();
-                        let entry_type = entry.base.file_type()?;
+                    self.draw_results()?;
+                }
 
-                        if entry_type.is_file() {
-                            remove_file(entry_name)?;
-                        } else if entry_type.is_dir() {
-                            remove_dir_all(entry_name)?;
+                // Content search only starts once the query is confirmed,
+                // after which Enter opens the selected match.
+                Action::Open if self.mode == Mode::Grep => {
+                    if self.grep.is_none() || self.grep_query != self.results_prompt.text() {
+                        if self.results_prompt.is_empty() {
+                            return Ok(false);
                         }
 
-                        self.entries = self.draw()?;
-                        self.selection = self
-                            .selection
-                            .min(self.entries.len().saturating_sub(1) as u16);
-                        self.entries = self.draw()?;
+                        let Ok(pattern) = grep::pattern(&self.search, self.results_prompt.text())
+                        else {
+                            return Ok(false);
+                        };
+
+                        self.grep = Some(Walk::spawn(
+                            self.path.clone(),
+                            self.show_hidden,
+                            move |entry, relative| grep::search(entry, relative, &pattern),
+                        ));
+                        self.grep_query = self.results_prompt.text().to_string();
+                        self.results_selection = 0;
+                        self.history.record(&self.results_prompt);
+                        self.draw_results()?;
+                        return Ok(false);
                     }
 
-                    _ => {}
-                },
+                    let Some((path, line)) = self.grep.as_ref().and_then(|grep| {
+                        grep.items
+                            .get(self.results_selection as usize)
+                            .map(|found| (grep.root.join(&found.path), found.line))
+                    }) else {
+                        return Ok(false);
+                    };
 
-                Mode::Add => match action {
-                    Action::Close => {
-                        self.mode = Mode::Normal;
-                        stdout().execute(cursor::Hide)?;
-                        self.add_buffer.clear();
-                        self.draw()?;
-                    }
+                    self.launch(self.opener.resolve(&path, Some(line)))?;
+                }
 
-                    Action::Add => {
-                        self.mode = Mode::Normal;
-                        let name = &mut self.add_buffer;
+                Action::Open => {
+                    self.update_matches();
+
+                    let Some(finder) = &self.finder else {
+                        return Ok(false);
+                    };
+
+                    let Some(path) = self
+                        .matches
+                        .found
+                        .get(self.results_selection as usize)
+                        .map(|(index, ..)| finder.root.join(&finder.items[*index]))
+                    else {
+                        return Ok(false);
+                    };
+
+                    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                        return Ok(false);
+                    };
+
+                    self.history.record(&self.results_prompt);
+                    self.set_path(parent.to_path_buf());
+
+                    self.mode = Mode::Normal;
+                    self.finder = None;
+                    self.search_prompt.clear();
+                    self.selection = 0;
+                    self.scroll = 0;
 
-                        stdout().execute(cursor::Hide)?;
+                    stdout().execute(cursor::Hide)?;
+                    self.entries = self.draw()?;
+                    self.select(&name.to_os_string())?;
+                    self.entries = self.draw()?;
+                }
 
-                        if name.ends_with('/') {
-                            create_dir(&name)?;
-                        } else {
-                            std::fs::File::create(&name)?;
-                        }
+                Action::Input(chord) => {
+                    let query = self.results_prompt.text().to_string();
 
-                        name.clear();
+                    if !self.results_prompt.edit(*chord, &self.history) {
+                        return Ok(false);
+                    }
 
-                        self.selection = self
-                            .selection
-                            .min(self.entries.len().saturating_sub(1) as u16);
-                        self.entries = self.draw()?;
+                    if self.mode == Mode::Find && query != self.results_prompt.text() {
+                        self.results_selection = 0;
                     }
 
-                    Action::Input(character) => match character {
-                        event::KeyCode::Backspace => {
-                            if self.add_buffer.is_empty() {
-                                break;
-                            }
+                    self.draw_results()?;
+                }
 
-                            self.move_left()?;
-                            self.add_buffer.remove(cursor::position()?.0 as usize - 4);
-                            self.redraw_add_buffer(1)?;
-                        }
+                _ => {}
+            },
 
-                        event::KeyCode::Enter => {
-                            self.mode = Mode::Normal;
-                            stdout().execute(cursor::Hide)?;
-                            self.entries = self.draw()?;
-                        }
+            Mode::Mark => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+                    self.mark_prompt.clear();
+                    self.draw()?;
+                }
 
-                        event::KeyCode::Left => {
-                            self.move_left()?;
-                        }
+                Action::MarkGlob => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+                    self.history.record(&self.mark_prompt);
 
-                        event::KeyCode::Right => {
-                            self.move_right(&self.add_buffer)?;
+                    for entry in self.entries.iter() {
+                        let name = entry.base.file_name();
+
+                        if glob::matches(self.mark_prompt.text(), &name::display(&name)) {
+                            self.marked.insert(name);
                         }
+                    }
 
-                        event::KeyCode::Char(character) => {
-                            self.add_buffer
-                                .insert(cursor::position()?.0 as usize - 4, *character);
+                    self.mark_prompt.clear();
+                    self.entries = self.draw()?;
+                }
 
-                            self.move_right(&self.add_buffer)?;
-                            self.redraw_add_buffer(0)?;
-                        }
+                Action::Redraw => self.entries = self.draw()?,
 
-                        _ => {}
-                    },
+                Action::Input(chord) => {
+                    self.mark_prompt.edit(*chord, &self.history);
+                    self.redraw_mark_prompt()?;
+                }
 
-                    _ => {}
-                },
+                _ => {}
+            },
 
-                Mode::Search => {
-                    match action {
-                        Action::Close => {
-                            self.mode = Mode::Normal;
-                            stdout().execute(cursor::Hide)?;
-                            self.search_buffer.clear();
-                            self.draw()?;
+            Mode::Link => match action {
+                Action::Close if self.link_prompt.close_menu() => self.entries = self.draw()?,
+
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+                    self.link_prompt.clear();
+                    self.draw()?;
+                }
+
+                Action::CycleLinkKind => {
+                    self.link_kind = self.link_kind.next();
+                    self.redraw_link_prompt()?;
+                }
+
+                Action::Link => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+
+                    self.history.record(&self.link_prompt);
+                    let destination = self
+                        .path
+                        .join(name::parse(&completion::expand(&self.link_prompt.take()))?);
+                    let targets = self.targets();
+
+                    // Into a directory the links keep the names of their
+                    // targets, and a single link can be given its own name.
+                    let paths = if destination.is_dir() {
+                        targets
+                            .iter()
+                            .map(|target| destination.join(target.file_name().unwrap()))
+                            .collect()
+                    } else if targets.len() == 1 {
+                        vec![destination.clone()]
+                    } else {
+                        self.show_message(format!(
+                            "{} is not a directory",
+                            name::display(destination.as_os_str())
+                        ))?;
+                        return Ok(false);
+                    };
+
+                    let mut change = Vec::new();
+                    let mut result = Ok(());
+
+                    for (target, path) in targets.into_iter().zip(paths) {
+                        result = link::create(self.link_kind, &target, &path)
+                            .and_then(|()| Operation::link(self.link_kind, target, path))
+                            .map(|operation| change.push(operation));
+
+                        if result.is_err() {
+                            break;
                         }
+                    }
 
-                        Action::Input(character) => match character {
-                            event::KeyCode::Backspace => {
-                                if self.search_buffer.is_empty() {
-                                    break;
-                                }
-
-                                self.move_left()?;
-                                self.search_buffer
-                                    .remove(cursor::position()?.0 as usize - 4);
-                                self.redraw_search_buffer()?;
-                            }
-
-                            event::KeyCode::Enter => {
-                                self.mode = Mode::Normal;
-                                stdout().execute(cursor::Hide)?;
-                                self.entries = self.draw()?;
-                            }
-
-                            event::KeyCode::Left => {
-                                self.move_left()?;
-                            }
-
-                            event::KeyCode::Right => {
-                                self.move_right(&self.search_buffer)?;
-                            }
-
-                            event::KeyCode::Char(character) => {
-                                self.search_buffer
-                                    .insert(cursor::position()?.0 as usize - 4, *character);
-
-                                self.move_right(&self.search_buffer)?;
-                                self.redraw_search_buffer()?;
-                            }
-
-                            _ => {}
-                        },
+                    self.journal.record(change);
+                    self.marked.clear();
 
-                        _ => {}
+                    match result {
+                        Ok(()) => self.entries = self.draw()?,
+                        Err(error) => self.show_message(format!("Linking stopped: {}", error))?,
                     }
+                }
 
-                    self.scroll = 0;
+                Action::Redraw => self.entries = self.draw()?,
+
+                Action::Complete => {
+                    self.link_prompt.complete(&self.path);
+                    self.redraw_path_prompt(false)?;
+                }
+
+                Action::Input(chord) => {
+                    let menu_was_shown = self.link_prompt.menu().is_some();
+                    self.link_prompt.edit(*chord, &self.history);
+                    self.redraw_path_prompt(menu_was_shown)?;
+                }
+
+                _ => {}
+            },
+
+            Mode::Permissions => match action {
+                Action::Redraw => {
                     self.entries = self.draw()?;
-                    self.selection = self
-                        .selection
-                        .min(self.entries.len().saturating_sub(1) as u16);
+
+                    if let Some(editor) = &self.permissions {
+                        editor.draw(&self.theme)?;
+                    }
+                }
+
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.permissions = None;
+                    stdout().execute(cursor::Hide)?;
                     self.entries = self.draw()?;
                 }
 
-                Mode::Help => match action {
-                    Action::Close => {
-                        self.mode = Mode::Normal;
-                        self.draw()?;
+                Action::EditPermissions => {
+                    self.mode = Mode::Normal;
+                    stdout().execute(cursor::Hide)?;
+
+                    let Some(editor) = self.permissions.take() else {
+                        return Ok(false);
+                    };
+
+                    editor.record(&mut self.history);
+                    self.marked.clear();
+
+                    match editor.apply() {
+                        Ok(()) => self.entries = self.draw()?,
+                        Err(error) => {
+                            self.show_message(format!("Changing permissions stopped: {}", error))?
+                        }
+                    }
+                }
+
+                Action::Input(chord) => {
+                    if let Some(editor) = &mut self.permissions {
+                        editor.input(*chord, &self.history);
+                        editor.draw(&self.theme)?;
                     }
+                }
 
-                    _ => {}
-                },
+                _ => {}
+            },
+
+            Mode::Conflict => match action {
+                Action::Close => {
+                    self.mode = Mode::Normal;
+
+                    if let Some(paste) = self.paste.take() {
+                        self.journal.record(paste.operations);
+                    }
+
+                    if let Some(register) = &mut self.register {
+                        register.paths.retain(|path| symlink_metadata(path).is_ok());
+                    }
+
+                    self.entries = self.draw()?;
+                }
+
+                Action::Resolve(policy) | Action::ResolveAll(policy) => {
+                    let all = matches!(action, Action::ResolveAll(_));
+                    let conflict = self.paste.as_mut().unwrap().resolve(*policy, all);
+                    self.continue_paste(conflict?)?;
+                }
+
+                _ => {}
+            },
+
+            Mode::Help => {
+                if let Action::Close = action {
+                    self.mode = Mode::Normal;
+                    self.draw()?;
+                }
             }
+
+            Mode::Message => match action {
+                Action::Redraw => {
+                    self.entries = self.draw()?;
+                    self.draw_message()?;
+                }
+
+                Action::Close => {
+                    self.mode = Mode::Normal;
+                    self.message.clear();
+                    self.draw()?;
+                }
+
+                _ => {}
+            },
         }
 
-        self.actions.clear();
-        Ok(())
+        Ok(true)
     }
 
+    /// Runs until closed. Errors from carrying out actions are reported in
+    /// a message, while any other error ends nfm with the terminal restored.
     pub fn run(&mut self) -> Result<()> {
+        let _guard = screen::Guard;
         self.initialize()?;
 
         while !self.should_close {
             self.handle_actions()?;
             self.handle_event()?;
+            self.handle_background()?;
         }
 
-        self.deinitialize()
+        Ok(())
+    }
+}
+
+/// The closest directory to `path`, itself included, that can be listed.
+fn listable(path: &Path) -> PathBuf {
+    let mut path = path;
+
+    while read_dir(path).is_err() {
+        let Some(parent) = path.parent() else {
+            break;
+        };
+
+        path = parent;
     }
+
+    path.to_path_buf()
+}
+
+/// What the user was doing when `action`, taken in `mode`, failed, for the
+/// message reporting it.
+fn operation(mode: &Mode, action: &Action) -> Option<&'static str> {
+    Some(match (mode, action) {
+        (Mode::Rename, _) | (Mode::Normal, Action::Rename) => "Renaming",
+        (Mode::BulkRename, _) | (Mode::Normal, Action::BulkRename) => "Bulk renaming",
+        (Mode::Remove, _) => "Moving to the trash",
+        (Mode::Delete, _) => "Deleting",
+        (Mode::Trash, Action::Restore) => "Restoring",
+        (Mode::Purge, _) | (Mode::Trash, Action::Purge) => "Purging",
+        (Mode::Normal, Action::ShowTrash) => "Opening the trash",
+        (Mode::Add, _) => "Creating",
+        (Mode::Link, _) => "Linking",
+        (Mode::Permissions, _) => "Changing permissions",
+        (Mode::Conflict, _) | (Mode::Normal, Action::Paste) => "Pasting",
+        (Mode::Normal, Action::Undo) => "Undoing",
+        (Mode::Normal, Action::Redo) => "Redoing",
+        (Mode::Find | Mode::Grep, Action::Open) | (Mode::Normal, Action::Open) => "Opening",
+        (Mode::Normal, Action::Back) => "Going back",
+        (Mode::Normal, Action::JumpToTarget) => "Going to the link target",
+        (Mode::Normal, Action::Find) => "Finding",
+        (Mode::Normal, Action::Grep) => "Searching file contents",
+        _ => return None,
+    })
 }
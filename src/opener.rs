@@ -0,0 +1,180 @@
+use std::{
+    env,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use serde::Deserialize;
+
+use crate::{glob, mime};
+
+#[derive(Clone)]
+pub enum Pattern {
+    Extension(Vec<String>),
+    Glob(String),
+    Mime(String),
+}
+
+impl Pattern {
+    fn matches(&self, path: &Path, mime: Option<&str>) -> bool {
+        match self {
+            Pattern::Extension(extensions) => path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .is_some_and(|extension| extensions.contains(&extension)),
+
+            Pattern::Glob(pattern) => path
+                .file_name()
+                .is_some_and(|name| glob::matches(pattern, &name.to_string_lossy())),
+
+            Pattern::Mime(pattern) => mime.is_some_and(|mime| glob::matches(pattern, mime)),
+        }
+    }
+}
+
+/// An `[[opener]]` entry of the configuration. Exactly one of `ext`, `glob`
+/// and `mime` selects the entries the command applies to.
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "RawRule")]
+pub struct Rule {
+    pub pattern: Pattern,
+    pub command: String,
+    pub detach: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    ext: Option<Vec<String>>,
+    glob: Option<String>,
+    mime: Option<String>,
+    command: String,
+    #[serde(default)]
+    detach: bool,
+}
+
+impl TryFrom<RawRule> for Rule {
+    type Error = String;
+
+    fn try_from(raw: RawRule) -> Result<Self, Self::Error> {
+        let pattern = match (raw.ext, raw.glob, raw.mime) {
+            (Some(extensions), None, None) => Pattern::Extension(
+                extensions
+                    .iter()
+                    .map(|e| e.trim_start_matches('.').to_lowercase())
+                    .collect(),
+            ),
+            (None, Some(glob), None) => Pattern::Glob(glob),
+            (None, None, Some(mime)) => Pattern::Mime(mime),
+            _ => return Err("opener needs exactly one of `ext`, `glob` and `mime`".to_string()),
+        };
+
+        Ok(Self {
+            pattern,
+            command: raw.command,
+            detach: raw.detach,
+        })
+    }
+}
+
+/// A command ready to be launched for an entry.
+pub struct Launch {
+    pub command: Command,
+    pub detach: bool,
+}
+
+impl Launch {
+    /// Opens `path` in `$VISUAL`/`$EDITOR` in the foreground.
+    pub fn editor(path: &Path) -> Self {
+        Self::new(&editor(), path, None, false)
+    }
+
+    /// Builds a launch for a `sh` command line, where `{}` stands for `path`
+    /// and `{line}` for `line`, or the first line when there is none. The
+    /// path is passed as a positional parameter so it never needs quoting.
+    fn new(command: &str, path: &Path, line: Option<usize>, detach: bool) -> Self {
+        let command = command.replace("{line}", &line.unwrap_or(1).to_string());
+        let script = if command.contains("{}") {
+            command.replace("{}", "\"$1\"")
+        } else {
+            format!("{} \"$1\"", command)
+        };
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script).arg("sh").arg(path);
+
+        if detach {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+        }
+
+        Self { command, detach }
+    }
+}
+
+pub struct Opener {
+    rules: Vec<Rule>,
+}
+
+impl Opener {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Resolves how `path` should be opened: the first matching rule wins,
+    /// text falls back to `$VISUAL`/`$EDITOR` and anything else is handed to
+    /// `xdg-open`. `line` is honoured by the editor and by rules that use
+    /// `{line}`.
+    pub fn resolve(&self, path: &Path, line: Option<usize>) -> Launch {
+        let mime = mime::sniff(path);
+
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.pattern.matches(path, mime))
+        {
+            return Launch::new(&rule.command, path, line, rule.detach);
+        }
+
+        match mime {
+            Some(mime) if !mime.starts_with("text/") => Launch::new("xdg-open", path, None, true),
+            _ => match line {
+                Some(line) => Launch::new(&format!("{} +{}", editor(), line), path, None, false),
+                None => Launch::editor(path),
+            },
+        }
+    }
+}
+
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|variable| env::var(variable).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "nvim".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, fs, os::unix::ffi::OsStrExt, process};
+
+    use super::*;
+
+    #[test]
+    fn pipes_are_resolved_without_being_read() {
+        let directory = env::temp_dir().join(format!("nfm-test-opener-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let pipe = directory.join("pipe");
+        let path = CString::new(pipe.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+
+        let launch = Opener::new(Vec::new()).resolve(&pipe, None);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(launch.detach);
+        assert_eq!(mime::sniff(Path::new("/nonexistent/nfm")), None);
+    }
+}
@@ -0,0 +1,547 @@
+use std::{
+    fs::{metadata, read_dir, set_permissions, symlink_metadata, Permissions},
+    io::{stdout, Error, Result, Write},
+    os::unix::fs::{chown, lchown, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    cursor,
+    event::KeyCode,
+    style::{self, StyledContent, Stylize},
+    terminal, QueueableCommand,
+};
+
+use crate::{
+    details::mode_bits,
+    history::History,
+    key::KeyChord,
+    line_editor::LineEditor,
+    name, text,
+    theme::Theme,
+    users::{self, group_name, user_name},
+    window::Window,
+};
+
+const WIDTH: u16 = 46;
+const HEIGHT: u16 = 16;
+
+/// Column of the first toggle, relative to the window.
+const TOGGLES: u16 = 13;
+/// Columns between the toggles of a row.
+const TOGGLE_SPACING: u16 = 8;
+/// Columns of the owner and group fields.
+const FIELD: usize = 16;
+
+/// Rows of the editor that focus moves between. The first four hold three
+/// toggles each.
+const USER: usize = 0;
+const SPECIAL: usize = 3;
+const OWNER: usize = 4;
+const GROUP: usize = 5;
+const RECURSIVE: usize = 6;
+
+/// The bit toggled at `row` and `column` of the grid of toggles.
+fn bit(row: usize, column: usize) -> u32 {
+    if row == SPECIAL {
+        0o4000 >> column
+    } else {
+        (0o4 >> column) << (3 * (2 - row))
+    }
+}
+
+/// The execute bits of the user, group and others.
+const EXECUTE: u32 = 0o111;
+
+/// Changes the mode bits, owner and group of a set of entries, starting from
+/// those of the first one.
+pub struct PermissionEditor {
+    targets: Vec<PathBuf>,
+    /// The mode of the first target as edited, which is what is shown.
+    mode: u32,
+    /// Bits to turn on and off in every target, leaving the others as they
+    /// were.
+    set: u32,
+    clear: u32,
+    owner: LineEditor,
+    group: LineEditor,
+    original_owner: String,
+    original_group: String,
+    recursive: bool,
+    /// Octal digits typed so far, which replace the mode as they come.
+    octal: String,
+    /// The mode and masks from before the digits were typed, to go back to
+    /// once they are all erased.
+    untyped: (u32, u32, u32),
+    row: usize,
+    column: usize,
+}
+
+impl PermissionEditor {
+    pub fn new(targets: Vec<PathBuf>) -> Result<Self> {
+        let first = metadata(&targets[0]).or_else(|_| symlink_metadata(&targets[0]))?;
+        let owner = user_name(first.uid()).unwrap_or_else(|| first.uid().to_string());
+        let group = group_name(first.gid()).unwrap_or_else(|| first.gid().to_string());
+
+        let field = |name, text: &String| {
+            let mut editor = LineEditor::new(name);
+            editor.set(text.clone());
+            editor
+        };
+
+        Ok(Self {
+            targets,
+            mode: first.mode() & 0o7777,
+            set: 0,
+            clear: 0,
+            owner: field("owner", &owner),
+            group: field("group", &group),
+            original_owner: owner,
+            original_group: group,
+            recursive: false,
+            octal: String::new(),
+            untyped: (0, 0, 0),
+            row: USER,
+            column: 0,
+        })
+    }
+
+    /// The owner or group field, when one has focus.
+    pub fn field_mut(&mut self) -> Option<&mut LineEditor> {
+        match self.row {
+            OWNER => Some(&mut self.owner),
+            GROUP => Some(&mut self.group),
+            _ => None,
+        }
+    }
+
+    /// Adds the owner and group to the histories of their fields when they
+    /// were edited.
+    pub fn record(&self, history: &mut History) {
+        if self.owner.text() != self.original_owner {
+            history.record(&self.owner);
+        }
+
+        if self.group.text() != self.original_group {
+            history.record(&self.group);
+        }
+    }
+
+    pub fn input(&mut self, chord: KeyChord, history: &History) {
+        let code = chord.code;
+
+        // The fields take what they can edit with, save for the keys moving
+        // between rows.
+        if !matches!(
+            code,
+            KeyCode::Up | KeyCode::Down | KeyCode::Tab | KeyCode::BackTab
+        ) {
+            if let Some(field) = self.field_mut() {
+                field.edit(chord, history);
+                return;
+            }
+        }
+
+        match code {
+            KeyCode::Up => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => self.row = (self.row + 1).min(RECURSIVE),
+            KeyCode::BackTab => self.row = self.row.saturating_sub(1),
+            KeyCode::Left if self.row <= SPECIAL => self.column = self.column.saturating_sub(1),
+            KeyCode::Right if self.row <= SPECIAL => self.column = (self.column + 1).min(2),
+
+            KeyCode::Char(' ') if self.row <= SPECIAL => {
+                let bit = bit(self.row, self.column);
+                self.mode ^= bit;
+
+                if self.mode & bit != 0 {
+                    self.set |= bit;
+                    self.clear &= !bit;
+                } else {
+                    self.clear |= bit;
+                    self.set &= !bit;
+                }
+
+                self.octal.clear();
+            }
+
+            KeyCode::Char(' ') if self.row == RECURSIVE => self.recursive = !self.recursive,
+
+            KeyCode::Char(digit @ '0'..='7') if self.row <= SPECIAL => {
+                if self.octal.len() == 4 {
+                    self.octal.clear();
+                }
+
+                if self.octal.is_empty() {
+                    self.untyped = (self.mode, self.set, self.clear);
+                }
+
+                self.octal.push(digit);
+                self.type_mode();
+            }
+
+            KeyCode::Backspace if self.row <= SPECIAL && !self.octal.is_empty() => {
+                self.octal.pop();
+
+                if self.octal.is_empty() {
+                    (self.mode, self.set, self.clear) = self.untyped;
+                } else {
+                    self.type_mode();
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Replaces the whole mode with the typed digits.
+    fn type_mode(&mut self) {
+        self.mode = u32::from_str_radix(&self.octal, 8).unwrap_or(self.mode);
+        self.set = self.mode;
+        self.clear = !self.mode & 0o7777;
+    }
+
+    /// Applies the bits that were changed to every target, and the owner and
+    /// group where they were changed. Recursively, symbolic links met inside
+    /// directories are changed themselves rather than followed, and keep
+    /// their mode, and execute bits are only added to directories and to
+    /// files that already have one, like the `X` of chmod.
+    pub fn apply(&self) -> Result<()> {
+        let uid = if self.owner.text() != self.original_owner {
+            Some(users::uid(self.owner.text())?)
+        } else {
+            None
+        };
+
+        let gid = if self.group.text() != self.original_group {
+            Some(users::gid(self.group.text())?)
+        } else {
+            None
+        };
+
+        for target in &self.targets {
+            self.change(target, uid, gid, true)?;
+        }
+
+        Ok(())
+    }
+
+    fn change(&self, path: &Path, uid: Option<u32>, gid: Option<u32>, top: bool) -> Result<()> {
+        let in_context = |error: Error| {
+            Error::new(
+                error.kind(),
+                format!("{}: {}", name::display(path.as_os_str()), error),
+            )
+        };
+        let is_link = symlink_metadata(path)
+            .map_err(in_context)?
+            .file_type()
+            .is_symlink();
+
+        if !top && is_link {
+            if uid.is_some() || gid.is_some() {
+                lchown(path, uid, gid).map_err(in_context)?;
+            }
+
+            return Ok(());
+        }
+
+        let metadata = metadata(path).map_err(in_context)?;
+        let old = metadata.mode() & 0o7777;
+        let mut set = self.set;
+
+        if !top && !metadata.is_dir() && old & EXECUTE == 0 {
+            set &= !EXECUTE;
+        }
+
+        let new = (old & !self.clear) | set;
+        let descend = self.recursive && metadata.is_dir();
+
+        // A directory is changed after its contents when it loses read or
+        // search permission, which could keep them out of reach.
+        if descend && old & !new & 0o555 != 0 {
+            self.change_contents(path, uid, gid)?;
+        }
+
+        if new != old {
+            set_permissions(path, Permissions::from_mode(new)).map_err(in_context)?;
+        }
+
+        if uid.is_some() || gid.is_some() {
+            if top {
+                chown(path, uid, gid).map_err(in_context)?;
+            } else {
+                lchown(path, uid, gid).map_err(in_context)?;
+            }
+        }
+
+        if descend && old & !new & 0o555 == 0 {
+            self.change_contents(path, uid, gid)?;
+        }
+
+        Ok(())
+    }
+
+    fn change_contents(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        let in_context = |error: Error| {
+            Error::new(
+                error.kind(),
+                format!("{}: {}", name::display(path.as_os_str()), error),
+            )
+        };
+
+        for entry in read_dir(path).map_err(in_context)? {
+            self.change(&entry.map_err(in_context)?.path(), uid, gid, false)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&self, theme: &Theme) -> Result<()> {
+        let terminal_size = terminal::size()?;
+        let window = Window::new(
+            (
+                (terminal_size.0 / 2).saturating_sub(WIDTH / 2),
+                (terminal_size.1 / 2).saturating_sub(HEIGHT / 2),
+            ),
+            (WIDTH, HEIGHT),
+        );
+        let (x, y) = window.position;
+
+        stdout().queue(style::SetAttribute(style::Attribute::Reset))?;
+
+        for row in y + 1..y + HEIGHT {
+            stdout()
+                .queue(cursor::MoveTo(x + 1, row))?
+                .queue(style::Print(" ".repeat(WIDTH as usize - 1)))?;
+        }
+
+        window.draw()?;
+
+        let title = match self.targets.as_slice() {
+            [target] => name::display(target.file_name().unwrap_or(target.as_os_str())),
+            targets => format!("{} entries", targets.len()),
+        };
+        let title = format!("Permissions of {}", title);
+        let title = text::truncate(&title, WIDTH as usize - 4).to_string();
+
+        stdout()
+            .queue(cursor::MoveTo(
+                x + (WIDTH / 2).saturating_sub(text::width(&title) as u16 / 2),
+                y + 1,
+            ))?
+            .queue(style::PrintStyledContent(title.with(theme.accent)))?;
+
+        let header = |row: u16, labels: [&str; 3]| -> Result<()> {
+            for (column, label) in labels.iter().enumerate() {
+                stdout()
+                    .queue(cursor::MoveTo(
+                        x + TOGGLES + column as u16 * TOGGLE_SPACING,
+                        row,
+                    ))?
+                    .queue(style::PrintStyledContent(label.with(theme.muted)))?;
+            }
+
+            Ok(())
+        };
+
+        let focus = |content: StyledContent<String>, focused: bool| {
+            if focused {
+                content.with(theme.accent).on(theme.selection).bold()
+            } else {
+                content
+            }
+        };
+
+        let toggle = |on: bool| if on { "[x]" } else { "[ ]" }.to_string();
+
+        header(y + 3, ["read", "write", "execute"])?;
+        header(y + 7, ["setuid", "setgid", "sticky"])?;
+
+        for (row, (label, line)) in [("user", y + 4), ("group", y + 5), ("other", y + 6)]
+            .into_iter()
+            .chain([("special", y + 8)])
+            .enumerate()
+        {
+            stdout()
+                .queue(cursor::MoveTo(x + 2, line))?
+                .queue(style::Print(label))?;
+
+            for column in 0..3 {
+                stdout()
+                    .queue(cursor::MoveTo(
+                        x + TOGGLES + column as u16 * TOGGLE_SPACING,
+                        line,
+                    ))?
+                    .queue(style::PrintStyledContent(focus(
+                        toggle(self.mode & bit(row, column) != 0).stylize(),
+                        self.row == row && self.column == column,
+                    )))?;
+            }
+        }
+
+        let field = |editor: &LineEditor, focused: bool| {
+            focus(text::fit(editor.text(), FIELD).underlined(), focused)
+        };
+
+        stdout()
+            .queue(cursor::MoveTo(x + 2, y + 10))?
+            .queue(style::Print("octal"))?
+            .queue(cursor::MoveTo(x + TOGGLES, y + 10))?
+            .queue(style::Print(format!(
+                "{:04o}  {}",
+                self.mode,
+                mode_bits(self.mode)
+            )))?
+            .queue(cursor::MoveTo(x + 2, y + 11))?
+            .queue(style::Print("owner"))?
+            .queue(cursor::MoveTo(x + TOGGLES, y + 11))?
+            .queue(style::PrintStyledContent(field(
+                &self.owner,
+                self.row == OWNER,
+            )))?
+            .queue(cursor::MoveTo(x + 2, y + 12))?
+            .queue(style::Print("group"))?
+            .queue(cursor::MoveTo(x + TOGGLES, y + 12))?
+            .queue(style::PrintStyledContent(field(
+                &self.group,
+                self.row == GROUP,
+            )))?
+            .queue(cursor::MoveTo(x + 2, y + 13))?
+            .queue(style::Print("recursive"))?
+            .queue(cursor::MoveTo(x + TOGGLES, y + 13))?
+            .queue(style::PrintStyledContent(focus(
+                toggle(self.recursive).stylize(),
+                self.row == RECURSIVE,
+            )))?
+            .queue(cursor::MoveTo(x + 2, y + 15))?
+            .queue(style::PrintStyledContent(
+                "Space toggle · 0-7 octal · Enter apply"
+                    .chars()
+                    .take(WIDTH as usize - 3)
+                    .collect::<String>()
+                    .with(theme.muted)
+                    .italic(),
+            ))?;
+
+        // The cursor shows where typing goes in the field in focus.
+        let focused = match self.row {
+            OWNER => Some((&self.owner, y + 11)),
+            GROUP => Some((&self.group, y + 12)),
+            _ => None,
+        };
+
+        match focused {
+            Some((editor, line)) => {
+                let column = editor.cursor_column().min(FIELD - 1) as u16;
+
+                stdout()
+                    .queue(cursor::MoveTo(x + TOGGLES + column, line))?
+                    .queue(cursor::Show)?;
+            }
+            None => {
+                stdout().queue(cursor::Hide)?;
+            }
+        }
+
+        stdout().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, os::unix::fs::symlink, process};
+
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn press(editor: &mut PermissionEditor, code: KeyCode) {
+        editor.input(KeyChord::new(code, KeyModifiers::NONE), &History::default());
+    }
+
+    fn mode(path: &Path) -> u32 {
+        symlink_metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn toggles_map_to_mode_bits() {
+        assert_eq!(bit(USER, 0), 0o400);
+        assert_eq!(bit(USER, 2), 0o100);
+        assert_eq!(bit(1, 1), 0o020);
+        assert_eq!(bit(2, 2), 0o001);
+        assert_eq!(bit(SPECIAL, 0), 0o4000);
+        assert_eq!(bit(SPECIAL, 2), 0o1000);
+
+        let all = (0..=SPECIAL)
+            .flat_map(|row| (0..3).map(move |column| bit(row, column)))
+            .fold(0, |all, bit| all | bit);
+        assert_eq!(all, 0o7777);
+    }
+
+    #[test]
+    fn typed_digits_replace_the_mode_until_erased() {
+        let directory = env::temp_dir().join(format!("nfm-test-octal-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::set_permissions(&directory, Permissions::from_mode(0o750)).unwrap();
+
+        let mut editor = PermissionEditor::new(vec![directory.clone()]).unwrap();
+        press(&mut editor, KeyCode::Char(' '));
+        for digit in "4755".chars() {
+            press(&mut editor, KeyCode::Char(digit));
+        }
+
+        assert_eq!(editor.mode, 0o4755);
+        assert_eq!((editor.set, editor.clear), (0o4755, 0o3022));
+
+        for _ in 0..4 {
+            press(&mut editor, KeyCode::Backspace);
+        }
+
+        assert_eq!(editor.mode, 0o350);
+        assert_eq!((editor.set, editor.clear), (0, 0o400));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn execute_is_only_added_inside_to_directories_and_executables() {
+        let directory = env::temp_dir().join(format!("nfm-test-execute-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("sub")).unwrap();
+        fs::write(directory.join("plain"), "").unwrap();
+        fs::write(directory.join("script"), "").unwrap();
+        fs::set_permissions(directory.join("plain"), Permissions::from_mode(0o600)).unwrap();
+        fs::set_permissions(directory.join("script"), Permissions::from_mode(0o700)).unwrap();
+        fs::set_permissions(directory.join("sub"), Permissions::from_mode(0o700)).unwrap();
+        fs::set_permissions(&directory, Permissions::from_mode(0o700)).unwrap();
+        symlink("plain", directory.join("link")).unwrap();
+
+        let mut editor = PermissionEditor::new(vec![directory.clone()]).unwrap();
+        editor.recursive = true;
+
+        // Group and others execute.
+        for key in [
+            KeyCode::Down,
+            KeyCode::Right,
+            KeyCode::Right,
+            KeyCode::Char(' '),
+            KeyCode::Down,
+            KeyCode::Char(' '),
+        ] {
+            press(&mut editor, key);
+        }
+
+        editor.apply().unwrap();
+
+        assert_eq!(mode(&directory), 0o711);
+        assert_eq!(mode(&directory.join("sub")), 0o711);
+        assert_eq!(mode(&directory.join("script")), 0o711);
+        assert_eq!(mode(&directory.join("plain")), 0o600);
+        assert!(symlink_metadata(directory.join("link"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
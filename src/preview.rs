@@ -0,0 +1,619 @@
+use std::{
+    fmt::Write,
+    fs::{metadata, read_dir, symlink_metadata, File},
+    io::{BufRead, BufReader, Read, Result, Seek, SeekFrom},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::SystemTime,
+};
+
+use flate2::read::GzDecoder;
+
+use crate::{mime, name};
+
+/// Most lines a preview holds, which is more than any pane shows.
+const LINES: usize = 200;
+
+/// Bytes shown in the hex dump of a binary file.
+const HEX_BYTES: usize = 256;
+
+/// How much of a file is read to identify it.
+const HEAD: usize = 64 * 1024;
+
+/// What a span of preview text is, for coloring.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Class {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Directory,
+    /// Headers and notes about the preview rather than the entry itself.
+    Muted,
+}
+
+pub type Line = Vec<(Class, String)>;
+
+/// The preview of an entry as it was at `modified`.
+pub struct Preview {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub lines: Vec<Line>,
+}
+
+impl Preview {
+    pub fn new(path: &Path) -> Self {
+        let modified = modified(path);
+        let lines = match symlink_metadata(path).and_then(|_| lines(path)) {
+            Ok(lines) => lines,
+            Err(error) => vec![muted(format!("Cannot preview: {}", error))],
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            modified,
+            lines,
+        }
+    }
+
+    /// Whether the preview still shows `path` as it is now.
+    pub fn is_current(&self, path: &Path) -> bool {
+        self.path == path && self.modified == modified(path)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    symlink_metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn muted(text: String) -> Line {
+    vec![(Class::Muted, text)]
+}
+
+fn lines(path: &Path) -> Result<Vec<Line>> {
+    let file_type = metadata(path)?.file_type();
+
+    if file_type.is_dir() {
+        return directory(path);
+    }
+
+    // Reading pipes and devices could block or never end, so they are only
+    // named.
+    if !file_type.is_file() {
+        let kind = if file_type.is_fifo() {
+            "Named pipe"
+        } else if file_type.is_socket() {
+            "Socket"
+        } else if file_type.is_block_device() {
+            "Block device"
+        } else {
+            "Character device"
+        };
+
+        return Ok(vec![muted(kind.to_string())]);
+    }
+
+    let mut head = Vec::new();
+    File::open(path)?.take(HEAD as u64).read_to_end(&mut head)?;
+
+    if let Some((format, width, height)) = image_size(&head) {
+        return Ok(vec![muted(format!(
+            "{} image, {}x{}",
+            format, width, height
+        ))]);
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if let Some(members) = archive(path, &name, &head)? {
+        let count = if members.len() < LINES {
+            members.len().to_string()
+        } else {
+            format!("{} or more", LINES)
+        };
+
+        let mut lines = vec![muted(format!("Archive, {} members", count)), vec![]];
+        lines.extend(
+            members
+                .into_iter()
+                .take(LINES)
+                .map(|member| vec![(Class::Plain, printable(&member))]),
+        );
+        return Ok(lines);
+    }
+
+    if mime::is_text(&head[..head.len().min(8192)]) {
+        return text(path, &name);
+    }
+
+    Ok(hex_dump(&head, mime::from_bytes(&head)))
+}
+
+fn directory(path: &Path) -> Result<Vec<Line>> {
+    let mut children = read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            (!is_dir, name::display(&entry.file_name()))
+        })
+        .collect::<Vec<_>>();
+
+    if children.is_empty() {
+        return Ok(vec![muted("Empty".to_string())]);
+    }
+
+    children.sort();
+
+    Ok(children
+        .into_iter()
+        .take(LINES)
+        .map(|(is_file, name)| {
+            if is_file {
+                vec![(Class::Plain, name)]
+            } else {
+                vec![(Class::Directory, format!("{}/", name))]
+            }
+        })
+        .collect())
+}
+
+fn hex_dump(head: &[u8], mime: &str) -> Vec<Line> {
+    let mut lines = vec![muted(mime.to_string()), vec![]];
+
+    for (row, chunk) in head.chunks(16).take(HEX_BYTES / 16).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+
+        lines.push(vec![
+            (Class::Muted, format!("{:08x}  ", row * 16)),
+            (Class::Plain, format!("{:<47}  ", hex)),
+            (Class::String, ascii),
+        ]);
+    }
+
+    lines
+}
+
+/// Recognizes PNG, GIF, BMP, JPEG and WebP headers, returning the format
+/// and the dimensions.
+fn image_size(head: &[u8]) -> Option<(&'static str, u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(head.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(head.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(head.get(at..at + 4)?.try_into().ok()?));
+    let le32 = |at: usize| Some(u32::from_le_bytes(head.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| Some(le32(at)? & 0xff_ffff);
+
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(("PNG", be32(16)?, be32(20)?));
+    }
+
+    if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        return Some(("GIF", le16(6)?, le16(8)?));
+    }
+
+    if head.starts_with(b"BM") && head.len() > 26 {
+        return Some(("BMP", le32(18)?, (le32(22)? as i32).unsigned_abs()));
+    }
+
+    if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        return match head.get(12..16)? {
+            b"VP8 " => Some(("WebP", le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = le32(21)?;
+                Some(("WebP", (bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some(("WebP", le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        };
+    }
+
+    if head.starts_with(b"\xff\xd8") {
+        let mut at = 2;
+
+        // Walks the segments up to the frame header holding the size.
+        while *head.get(at)? == 0xff {
+            let marker = *head.get(at + 1)?;
+
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                return Some(("JPEG", be16(at + 7)?, be16(at + 5)?));
+            }
+
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+
+    None
+}
+
+/// Lists the members of zip and tar archives, including gzip-compressed
+/// tarballs. Returns `None` for anything else.
+fn archive(path: &Path, name: &str, head: &[u8]) -> Result<Option<Vec<String>>> {
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        return zip_members(path).map(Some);
+    }
+
+    if head.starts_with(b"\x1f\x8b") && (name.ends_with(".tar.gz") || name.ends_with(".tgz")) {
+        return tar_members(GzDecoder::new(File::open(path)?)).map(Some);
+    }
+
+    if head.get(257..262) == Some(b"ustar") {
+        return tar_members(File::open(path)?).map(Some);
+    }
+
+    Ok(None)
+}
+
+fn zip_members(path: &Path) -> Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+
+    // The end of central directory record sits at the end of the file, after
+    // a comment of at most 64 KiB.
+    let tail_length = length.min(22 + 0xffff);
+    let mut tail = vec![0; tail_length as usize];
+    file.seek(SeekFrom::Start(length - tail_length))?;
+    file.read_exact(&mut tail)?;
+
+    let Some(end) = tail.windows(4).rposition(|window| window == b"PK\x05\x06") else {
+        return Ok(Vec::new());
+    };
+
+    let record = &tail[end..];
+
+    if record.len() < 22 {
+        return Ok(Vec::new());
+    }
+
+    let size = u32::from_le_bytes(record[12..16].try_into().unwrap()) as u64;
+    let offset = u32::from_le_bytes(record[16..20].try_into().unwrap()) as u64;
+
+    // A damaged record could claim more than the file holds.
+    let mut directory = vec![0; size.min(length.saturating_sub(offset)) as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut directory)?;
+
+    let mut members = Vec::new();
+    let mut at = 0;
+
+    while directory.get(at..at + 4) == Some(b"PK\x01\x02") && members.len() < LINES {
+        let field = |offset: usize| {
+            directory
+                .get(at + offset..at + offset + 2)
+                .map_or(0, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+        };
+        let (name, extra, comment) = (field(28), field(30), field(32));
+
+        let Some(name) = directory.get(at + 46..at + 46 + name) else {
+            break;
+        };
+
+        members.push(String::from_utf8_lossy(name).into_owned());
+        at += 46 + name.len() + extra + comment;
+    }
+
+    Ok(members)
+}
+
+fn tar_members(mut reader: impl Read) -> Result<Vec<String>> {
+    let mut members = Vec::new();
+    let mut header = [0; 512];
+
+    while members.len() < LINES && reader.read_exact(&mut header).is_ok() {
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+
+        let field = |range: std::ops::Range<usize>| {
+            let bytes = &header[range];
+            let end = bytes
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+
+        let mut name = field(0..100);
+
+        if &header[257..262] == b"ustar" && header[345] != 0 {
+            name = format!("{}/{}", field(345..500), name);
+        }
+
+        let size = u64::from_str_radix(field(124..136).trim(), 8).unwrap_or(0);
+
+        // Long names and extended headers describe the next member.
+        if !matches!(header[156], b'L' | b'K' | b'x' | b'g') {
+            members.push(name);
+        }
+
+        std::io::copy(
+            &mut (&mut reader).take(size.div_ceil(512) * 512),
+            &mut std::io::sink(),
+        )?;
+    }
+
+    Ok(members)
+}
+
+/// A rough idea of a language's syntax, enough to color a preview.
+struct Syntax {
+    comment: &'static [&'static str],
+    keywords: &'static [&'static str],
+}
+
+const C_LIKE: &[&str] = &[
+    "break",
+    "case",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extern",
+    "false",
+    "fn",
+    "for",
+    "func",
+    "function",
+    "if",
+    "impl",
+    "import",
+    "in",
+    "interface",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "mut",
+    "new",
+    "null",
+    "package",
+    "private",
+    "pub",
+    "public",
+    "return",
+    "self",
+    "static",
+    "struct",
+    "switch",
+    "this",
+    "trait",
+    "true",
+    "type",
+    "use",
+    "var",
+    "void",
+    "where",
+    "while",
+];
+
+const SCRIPT: &[&str] = &[
+    "and", "as", "case", "class", "def", "do", "done", "elif", "else", "end", "esac", "export",
+    "false", "fi", "for", "from", "function", "if", "import", "in", "lambda", "local", "nil",
+    "None", "not", "or", "pass", "return", "then", "True", "False", "true", "while", "with",
+    "yield",
+];
+
+fn syntax(name: &str) -> Syntax {
+    let extension = name.rsplit_once('.').map_or("", |(_, extension)| extension);
+
+    match extension {
+        "rs" | "c" | "h" | "cpp" | "hpp" | "cc" | "go" | "java" | "js" | "ts" | "jsx" | "tsx"
+        | "css" | "odin" | "zig" | "kt" | "swift" | "cs" => Syntax {
+            comment: &["//"],
+            keywords: C_LIKE,
+        },
+        "py" | "sh" | "bash" | "zsh" | "fish" | "rb" | "pl" | "toml" | "yaml" | "yml" | "conf"
+        | "ini" | "cmake" | "mk" => Syntax {
+            comment: &["#"],
+            keywords: SCRIPT,
+        },
+        "lua" | "sql" | "hs" => Syntax {
+            comment: &["--"],
+            keywords: SCRIPT,
+        },
+        "vim" => Syntax {
+            comment: &["\""],
+            keywords: SCRIPT,
+        },
+        "ml" => Syntax {
+            comment: &["(*"],
+            keywords: SCRIPT,
+        },
+        _ if name == "makefile" || name == "dockerfile" => Syntax {
+            comment: &["#"],
+            keywords: SCRIPT,
+        },
+        _ => Syntax {
+            comment: &[],
+            keywords: &[],
+        },
+    }
+}
+
+fn text(path: &Path, name: &str) -> Result<Vec<Line>> {
+    let syntax = syntax(name);
+
+    BufReader::new(File::open(path)?)
+        .split(b'\n')
+        .take(LINES)
+        .map(|line| {
+            let line = String::from_utf8_lossy(&line?).into_owned();
+            Ok(highlight(&printable(line.trim_end_matches('\r')), &syntax))
+        })
+        .collect()
+}
+
+/// Expands tabs and writes other control characters as `\xNN`, the way
+/// `name::display` does, so that escape sequences in a file cannot move the
+/// cursor or change colors when the preview is drawn.
+fn printable(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+
+    for character in line.chars() {
+        if character == '\t' {
+            text.push_str("    ");
+        } else if character.is_control() {
+            let mut buffer = [0; 4];
+
+            for byte in character.encode_utf8(&mut buffer).bytes() {
+                let _ = write!(text, "\\x{:02x}", byte);
+            }
+        } else {
+            text.push(character);
+        }
+    }
+
+    text
+}
+
+/// Splits `line` into comments, strings, numbers, keywords and the rest.
+fn highlight(line: &str, syntax: &Syntax) -> Line {
+    let mut spans: Line = Vec::new();
+    let mut push = |class: Class, text: &str| match spans.last_mut() {
+        Some((last, span)) if *last == class => span.push_str(text),
+        _ => spans.push((class, text.to_string())),
+    };
+
+    let mut rest = line;
+
+    while let Some(character) = rest.chars().next() {
+        if syntax
+            .comment
+            .iter()
+            .any(|comment| rest.starts_with(comment))
+        {
+            push(Class::Comment, rest);
+            break;
+        }
+
+        let length = if character == '"' || (character == '\'' && !syntax.keywords.is_empty()) {
+            let closing = rest[1..]
+                .char_indices()
+                .scan(false, |escaped, (index, current)| {
+                    let found = !*escaped && current == character;
+                    *escaped = !*escaped && current == '\\';
+                    Some((index, found))
+                })
+                .find(|(_, found)| *found)
+                .map_or(rest.len(), |(index, _)| index + 2);
+
+            push(Class::String, &rest[..closing]);
+            closing
+        } else if character.is_alphanumeric() || character == '_' {
+            let length = rest
+                .find(|current: char| !(current.is_alphanumeric() || current == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..length];
+
+            push(
+                if character.is_ascii_digit() {
+                    Class::Number
+                } else if syntax.keywords.contains(&word) {
+                    Class::Keyword
+                } else {
+                    Class::Plain
+                },
+                word,
+            );
+            length
+        } else {
+            push(Class::Plain, &rest[..character.len_utf8()]);
+            character.len_utf8()
+        };
+
+        rest = &rest[length..];
+    }
+
+    spans
+}
+
+/// Computes previews on a thread of its own, so slow disks and large files
+/// do not hold up input.
+pub struct Previewer {
+    requests: Sender<PathBuf>,
+    previews: Receiver<Preview>,
+}
+
+impl Previewer {
+    pub fn spawn() -> Self {
+        let (requests, incoming) = mpsc::channel::<PathBuf>();
+        let (outgoing, previews) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Ok(mut path) = incoming.recv() {
+                // Only the latest request matters when the selection moved on
+                // in the meantime.
+                while let Ok(newer) = incoming.try_recv() {
+                    path = newer;
+                }
+
+                if outgoing.send(Preview::new(&path)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { requests, previews }
+    }
+
+    pub fn request(&self, path: PathBuf) {
+        let _ = self.requests.send(path);
+    }
+
+    /// The most recent preview computed since the last call, if any.
+    pub fn poll(&self) -> Option<Preview> {
+        let mut latest = None;
+
+        loop {
+            match self.previews.try_recv() {
+                Ok(preview) => latest = Some(preview),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return latest,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_characters_are_written_out() {
+        assert_eq!(printable("\tlet\x1b[2Jx = 1;"), "    let\\x1b[2Jx = 1;");
+        assert_eq!(printable("a\u{9b}31mb\x7f"), "a\\xc2\\x9b31mb\\x7f");
+
+        let spans = highlight(&printable("\x1b]0;title\x07 // done"), &syntax("main.rs"));
+        let text = spans
+            .iter()
+            .map(|(_, span)| span.as_str())
+            .collect::<String>();
+        assert_eq!(text, "\\x1b]0;title\\x07 // done");
+        assert!(spans
+            .iter()
+            .all(|(_, span)| !span.contains(char::is_control)));
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::HashSet,
+    env,
+    ffi::{OsStr, OsString},
+    fs::{remove_file, rename, symlink_metadata, OpenOptions},
+    io::{Error, ErrorKind, Result, Write},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::OpenOptionsExt,
+    },
+    path::{Path, PathBuf},
+    process,
+    time::SystemTime,
+};
+
+use crate::{journal::Operation, name};
+
+/// Renames within one directory worked out from an edited listing, ordered
+/// so that swaps and cycles go through temporary names.
+pub struct RenamePlan {
+    /// What the user asked for, as pairs of old and new names.
+    pub renames: Vec<(OsString, OsString)>,
+    steps: Vec<(OsString, OsString)>,
+}
+
+impl RenamePlan {
+    /// Writes `names` one per line, the way they are handed to the editor.
+    pub fn listing(names: &[OsString]) -> Vec<u8> {
+        names
+            .iter()
+            .flat_map(|name| name.as_bytes().iter().chain(b"\n"))
+            .copied()
+            .collect()
+    }
+
+    /// Compares the edited listing with `names`, line by line, and plans the
+    /// renames it describes in `directory`.
+    pub fn new(directory: &Path, names: &[OsString], edited: &[u8]) -> Result<Self> {
+        let edited = edited
+            .strip_suffix(b"\n")
+            .unwrap_or(edited)
+            .split(|&byte| byte == b'\n')
+            .map(|line| OsString::from_vec(line.strip_suffix(b"\r").unwrap_or(line).to_vec()))
+            .collect::<Vec<_>>();
+
+        if edited.len() != names.len() {
+            return Err(invalid(format!(
+                "expected {} lines but found {}, lines must not be added or removed",
+                names.len(),
+                edited.len()
+            )));
+        }
+
+        let renames = names
+            .iter()
+            .cloned()
+            .zip(edited)
+            .filter(|(old, new)| old != new)
+            .collect::<Vec<_>>();
+
+        let sources = renames
+            .iter()
+            .map(|(old, _)| old.clone())
+            .collect::<HashSet<_>>();
+        let mut targets = HashSet::new();
+
+        for (_, new) in renames.iter() {
+            let bytes = new.as_bytes();
+
+            if bytes.is_empty()
+                || bytes == b"."
+                || bytes == b".."
+                || bytes.contains(&b'/')
+                || bytes.contains(&b'\0')
+            {
+                return Err(invalid(format!(
+                    "`{}` is not a valid name",
+                    name::display(new)
+                )));
+            }
+
+            if !targets.insert(new.clone()) {
+                return Err(invalid(format!(
+                    "more than one entry would be named `{}`",
+                    name::display(new)
+                )));
+            }
+
+            if !sources.contains(new) && symlink_metadata(directory.join(new)).is_ok() {
+                return Err(invalid(format!("`{}` already exists", name::display(new))));
+            }
+        }
+
+        let steps = Self::order(directory, &renames);
+
+        Ok(Self { renames, steps })
+    }
+
+    /// Orders the renames so that no step overwrites an entry that still has
+    /// to be renamed. When only cycles are left, one of their entries is
+    /// moved out of the way under a temporary name first.
+    fn order(directory: &Path, renames: &[(OsString, OsString)]) -> Vec<(OsString, OsString)> {
+        let mut pending = renames.to_vec();
+        let mut steps = Vec::new();
+        let mut temporary = 0;
+
+        while !pending.is_empty() {
+            let free = pending
+                .iter()
+                .position(|(_, new)| !pending.iter().any(|(old, _)| old == new));
+
+            match free {
+                Some(index) => steps.push(pending.remove(index)),
+
+                None => {
+                    let name = loop {
+                        temporary += 1;
+
+                        let name = OsString::from(format!(".nfm-rename-{}", temporary));
+
+                        if symlink_metadata(directory.join(&name)).is_err()
+                            && !renames
+                                .iter()
+                                .any(|(old, new)| *old == name || *new == name)
+                        {
+                            break name;
+                        }
+                    };
+
+                    let old = std::mem::replace(&mut pending[0].0, name.clone());
+                    steps.push((old, name));
+                }
+            }
+        }
+
+        steps
+    }
+
+    /// Performs the renames in `directory`, stopping at the first failure.
+    /// Each step that went through is pushed onto `done` for the journal.
+    pub fn apply(&self, directory: &Path, done: &mut Vec<Operation>) -> Result<()> {
+        for (old, new) in self.steps.iter() {
+            let (from, to) = (directory.join(old), directory.join(new));
+
+            if symlink_metadata(&to).is_ok() {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} already exists", name::display(to.as_os_str())),
+                ));
+            }
+
+            rename(&from, &to)?;
+            done.push(Operation::rename(from, to)?);
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Whether `name` can be written on a line of its own, and read back even
+/// when the editor ends lines with `\r\n`.
+pub fn fits_listing(name: &OsStr) -> bool {
+    !name.as_bytes().contains(&b'\n') && !name.as_bytes().ends_with(b"\r")
+}
+
+/// A temporary file holding a listing, readable only by the user and removed
+/// when dropped.
+pub struct Listing {
+    pub path: PathBuf,
+}
+
+impl Listing {
+    /// Creates the file under a name that was not taken and writes `contents`.
+    pub fn create(contents: &[u8]) -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+
+        for attempt in 0..100 {
+            let path = env::temp_dir().join(format!(
+                "nfm-rename-{}-{:x}.txt",
+                process::id(),
+                nanos.wrapping_add(attempt)
+            ));
+
+            let mut file = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+            {
+                Ok(file) => file,
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            };
+
+            let listing = Self { path };
+            file.write_all(contents)?;
+            return Ok(listing);
+        }
+
+        Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "could not find a free name for the listing",
+        ))
+    }
+}
+
+impl Drop for Listing {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use super::*;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs
+            .iter()
+            .map(|(old, new)| (OsString::from(old), OsString::from(new)))
+            .collect()
+    }
+
+    /// Carries out `steps` on entries named `names`, checking that none of
+    /// them overwrites an entry, and returns where each entry ended up.
+    fn simulate(names: &[&str], steps: &[(OsString, OsString)]) -> BTreeMap<String, String> {
+        let mut entries = names
+            .iter()
+            .map(|name| (OsString::from(name), name.to_string()))
+            .collect::<BTreeMap<_, _>>();
+
+        for (old, new) in steps {
+            let entry = entries.remove(old).expect("renamed entry is missing");
+            assert!(
+                entries.insert(new.clone(), entry).is_none(),
+                "{:?} overwritten",
+                new
+            );
+        }
+
+        entries
+            .into_iter()
+            .map(|(name, entry)| (entry, name.into_string().unwrap()))
+            .collect()
+    }
+
+    fn outcome(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(old, new)| (old.to_string(), new.to_string()))
+            .collect()
+    }
+
+    fn missing() -> &'static Path {
+        Path::new("/nonexistent/nfm")
+    }
+
+    #[test]
+    fn chains_are_renamed_from_the_end() {
+        let steps = RenamePlan::order(missing(), &renames(&[("a", "b"), ("b", "c")]));
+
+        assert_eq!(steps, renames(&[("b", "c"), ("a", "b")]));
+    }
+
+    #[test]
+    fn swaps_go_through_a_temporary_name() {
+        let steps = RenamePlan::order(missing(), &renames(&[("a", "b"), ("b", "a")]));
+
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].1, OsString::from(".nfm-rename-1"));
+        assert_eq!(
+            simulate(&["a", "b"], &steps),
+            outcome(&[("a", "b"), ("b", "a")])
+        );
+    }
+
+    #[test]
+    fn cycles_and_chains_end_where_asked() {
+        let pairs = [("a", "b"), ("b", "c"), ("c", "a"), ("d", "e"), ("e", "f")];
+        let steps = RenamePlan::order(missing(), &renames(&pairs));
+
+        assert_eq!(steps.len(), pairs.len() + 1);
+        assert_eq!(
+            simulate(&["a", "b", "c", "d", "e"], &steps),
+            outcome(&pairs)
+        );
+    }
+
+    #[test]
+    fn temporary_names_avoid_the_renamed_names() {
+        let pairs = [
+            (".nfm-rename-1", ".nfm-rename-2"),
+            (".nfm-rename-2", ".nfm-rename-1"),
+        ];
+        let steps = RenamePlan::order(missing(), &renames(&pairs));
+
+        assert_eq!(steps[0].1, OsString::from(".nfm-rename-3"));
+        assert_eq!(
+            simulate(&[".nfm-rename-1", ".nfm-rename-2"], &steps),
+            outcome(&pairs)
+        );
+    }
+
+    #[test]
+    fn names_no_entry_can_hold_are_refused() {
+        let names = [OsString::from("a")];
+
+        for edited in [&b"\n"[..], b".\n", b"..\n", b"b/c\n", b"b\0c\n"] {
+            let error = RenamePlan::new(missing(), &names, edited).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{:?}", edited);
+        }
+
+        assert!(RenamePlan::new(missing(), &names, b"b\r\n").is_ok());
+    }
+
+    #[test]
+    fn temporary_names_avoid_existing_entries() {
+        let directory = env::temp_dir().join(format!("nfm-test-order-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join(".nfm-rename-1"), "").unwrap();
+
+        let steps = RenamePlan::order(&directory, &renames(&[("a", "b"), ("b", "a")]));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(steps[0].1, OsString::from(".nfm-rename-2"));
+    }
+}
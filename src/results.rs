@@ -0,0 +1,155 @@
+use std::{
+    io::{stdout, Result, Write},
+    ops::Range,
+};
+
+use crossterm::{
+    cursor,
+    style::{self, StyledContent, Stylize},
+    terminal, QueueableCommand,
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{line_editor::LineEditor, text, theme::Theme};
+
+pub struct Row {
+    /// Drawn muted before the text, such as `path:line:`.
+    pub prefix: String,
+    pub text: String,
+    /// Indices of the characters of `text` to highlight.
+    pub highlights: Vec<usize>,
+}
+
+/// A full-screen list of results with a query prompt on the bottom line,
+/// as used by the finder and content search.
+pub struct ResultList<'a> {
+    pub title: String,
+    pub hint: String,
+    /// The rows in view, as picked by `visible`.
+    pub rows: Vec<Row>,
+    /// The index of the first row in view.
+    pub first: usize,
+    pub selection: u16,
+    /// Shown in place of the rows when there are none.
+    pub empty: &'a str,
+    pub query: &'a LineEditor,
+    /// Describes how the query is matched, drawn at the end of the prompt.
+    pub label: String,
+    pub invalid: bool,
+}
+
+impl ResultList<'_> {
+    /// Which of `len` rows fit on the screen with the one at `selection` in
+    /// view, so that only those need to be built.
+    pub fn visible(selection: u16, len: usize) -> Result<Range<usize>> {
+        let visible = terminal::size()?.1.saturating_sub(4).max(1) as usize;
+        let first = (selection as usize).saturating_sub(visible - 1);
+        let end = len.min(first + visible);
+
+        Ok(first.min(end)..end)
+    }
+
+    pub fn draw(&self, theme: &Theme) -> Result<()> {
+        let terminal_size = terminal::size()?;
+
+        stdout()
+            .queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::PrintStyledContent(
+                format!(
+                    " {}{:>padding$}",
+                    self.title.clone().with(theme.accent).italic(),
+                    self.hint,
+                    padding =
+                        (terminal_size.0 as usize).saturating_sub(text::width(&self.title) + 1),
+                )
+                .stylize(),
+            ))?
+            .queue(cursor::MoveToNextLine(2))?;
+
+        if self.rows.is_empty() {
+            stdout().queue(style::PrintStyledContent(
+                format!(" {}", self.empty).with(theme.muted).italic(),
+            ))?;
+        }
+
+        for (index, row) in self.rows.iter().enumerate() {
+            let selected = self.first + index == self.selection as usize;
+            let style = |content: StyledContent<String>| {
+                if selected {
+                    content.on(theme.selection).bold()
+                } else {
+                    content
+                }
+            };
+
+            let width = terminal_size.0 as usize;
+            let mut used = text::width(&row.prefix) + 1;
+            let mut index = 0;
+
+            stdout().queue(style::PrintStyledContent(style(
+                format!(" {}", row.prefix).with(theme.muted),
+            )))?;
+
+            for grapheme in row.text.graphemes(true) {
+                let grapheme_width = text::width(grapheme);
+
+                if used + grapheme_width > width {
+                    break;
+                }
+
+                let characters = grapheme.chars().count();
+                let highlighted =
+                    (index..index + characters).any(|index| row.highlights.contains(&index));
+                let content = grapheme.to_string().stylize();
+
+                index += characters;
+
+                stdout().queue(style::PrintStyledContent(style(if highlighted {
+                    content.with(theme.accent).underlined()
+                } else {
+                    content
+                })))?;
+
+                used += grapheme_width;
+            }
+
+            if selected {
+                stdout().queue(style::PrintStyledContent(style(
+                    " ".repeat(width.saturating_sub(used)).stylize(),
+                )))?;
+            }
+
+            stdout().queue(cursor::MoveToNextLine(1))?;
+        }
+
+        stdout()
+            .queue(cursor::MoveTo(1, terminal_size.1 - 1))?
+            .queue(style::Print(""))?
+            .queue(cursor::MoveTo(
+                (terminal_size.0 as usize).saturating_sub(self.label.len() + 1) as u16,
+                terminal_size.1 - 1,
+            ))?
+            .queue(style::PrintStyledContent(
+                self.label
+                    .clone()
+                    .with(if self.invalid {
+                        theme.danger
+                    } else {
+                        theme.muted
+                    })
+                    .italic(),
+            ))?
+            .queue(cursor::MoveTo(4, terminal_size.1 - 1))?
+            .queue(style::PrintStyledContent(
+                self.query.text().to_string().underlined(),
+            ))?
+            .queue(cursor::MoveTo(
+                4 + self.query.cursor_column() as u16,
+                terminal_size.1 - 1,
+            ))?;
+
+        stdout().flush()
+    }
+}
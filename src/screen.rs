@@ -0,0 +1,39 @@
+use std::{
+    io::{stdout, Result},
+    panic,
+};
+
+use crossterm::{cursor, event, terminal, ExecutableCommand};
+
+/// Leaves raw mode and the alternate screen, giving the terminal back to the
+/// shell.
+pub fn restore() -> Result<()> {
+    terminal::disable_raw_mode()?;
+
+    stdout()
+        .execute(event::DisableBracketedPaste)?
+        .execute(cursor::Show)?
+        .execute(terminal::LeaveAlternateScreen)?;
+
+    Ok(())
+}
+
+/// Restores the terminal when dropped, however the main loop ends.
+pub struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let _ = restore();
+    }
+}
+
+/// Restores the terminal before a panic is reported, so that the message is
+/// not lost with the alternate screen and the shell is left usable.
+pub fn install_panic_hook() {
+    let hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+}
@@ -0,0 +1,364 @@
+use std::{
+    cmp::Reverse,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::{glob, name};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatcherKind {
+    /// The name contains the pattern.
+    #[default]
+    Substring,
+    /// The pattern's characters appear in the name in order. Results are
+    /// ranked by how well they match.
+    Fuzzy,
+    Regex,
+    /// The whole name matches a shell-style glob such as `*.rs`.
+    Glob,
+}
+
+impl MatcherKind {
+    pub fn next(self) -> Self {
+        match self {
+            MatcherKind::Substring => MatcherKind::Fuzzy,
+            MatcherKind::Fuzzy => MatcherKind::Regex,
+            MatcherKind::Regex => MatcherKind::Glob,
+            MatcherKind::Glob => MatcherKind::Substring,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MatcherKind::Substring => "substring",
+            MatcherKind::Fuzzy => "fuzzy",
+            MatcherKind::Regex => "regex",
+            MatcherKind::Glob => "glob",
+        }
+    }
+}
+
+/// The `[search]` table, which the search prompt can change on the fly.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Search {
+    pub matcher: MatcherKind,
+    /// Match case only when the pattern contains an uppercase letter. When
+    /// off, case is always ignored.
+    pub smart_case: bool,
+}
+
+impl Search {
+    pub fn label(&self) -> String {
+        if self.smart_case {
+            format!("{}, smart case", self.matcher.name())
+        } else {
+            self.matcher.name().to_string()
+        }
+    }
+}
+
+pub struct Match {
+    /// Higher is better. Only fuzzy matching tells matches apart.
+    pub score: i64,
+    /// Indices of the matched characters, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+enum Pattern {
+    Substring(Vec<char>),
+    Fuzzy(Vec<char>),
+    Regex(Regex),
+    Glob(String),
+}
+
+/// A compiled search pattern.
+pub struct Matcher {
+    pattern: Pattern,
+    ignore_case: bool,
+}
+
+impl Matcher {
+    pub fn new(search: &Search, pattern: &str) -> Result<Self> {
+        let ignore_case = !(search.smart_case && pattern.chars().any(char::is_uppercase));
+        let folded = pattern
+            .chars()
+            .map(|character| fold(character, ignore_case))
+            .collect::<Vec<_>>();
+
+        let pattern = match search.matcher {
+            MatcherKind::Substring => Pattern::Substring(folded),
+            MatcherKind::Fuzzy => Pattern::Fuzzy(folded),
+            MatcherKind::Regex => Pattern::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .map_err(|error| Error::new(ErrorKind::InvalidInput, error.to_string()))?,
+            ),
+            MatcherKind::Glob => Pattern::Glob(folded.into_iter().collect()),
+        };
+
+        Ok(Self {
+            pattern,
+            ignore_case,
+        })
+    }
+
+    /// Matches `text`, returning `None` if it does not match at all.
+    pub fn find(&self, text: &str) -> Option<Match> {
+        let folded = || {
+            text.chars()
+                .map(|character| fold(character, self.ignore_case))
+                .collect::<Vec<_>>()
+        };
+
+        match &self.pattern {
+            Pattern::Substring(pattern) => {
+                let text = folded();
+
+                if pattern.is_empty() {
+                    return Some(Match {
+                        score: 0,
+                        positions: Vec::new(),
+                    });
+                }
+
+                let start = text
+                    .windows(pattern.len())
+                    .position(|window| window == pattern.as_slice())?;
+
+                Some(Match {
+                    score: 0,
+                    positions: (start..start + pattern.len()).collect(),
+                })
+            }
+
+            Pattern::Fuzzy(pattern) => fuzzy(pattern, &folded()),
+
+            Pattern::Regex(regex) => {
+                let found = regex.find(text)?;
+
+                Some(Match {
+                    score: 0,
+                    positions: text
+                        .char_indices()
+                        .enumerate()
+                        .filter(|(_, (byte, _))| found.range().contains(byte))
+                        .map(|(index, _)| index)
+                        .collect(),
+                })
+            }
+
+            Pattern::Glob(pattern) => {
+                glob::matches(pattern, &folded().into_iter().collect::<String>()).then(|| Match {
+                    score: 0,
+                    positions: Vec::new(),
+                })
+            }
+        }
+    }
+}
+
+/// The paths matching a query, best first with fuzzy matching. Paths found
+/// since the last update are matched on their own, and everything is
+/// matched again only when the query or the way it is matched changes.
+#[derive(Default)]
+pub struct Matches {
+    query: String,
+    search: Option<Search>,
+    /// How many paths have been matched so far.
+    seen: usize,
+    /// Indices of the matching paths with their score and the characters to
+    /// highlight.
+    pub found: Vec<(usize, i64, Vec<usize>)>,
+}
+
+impl Matches {
+    pub fn update(&mut self, paths: &[PathBuf], search: &Search, query: &str) {
+        if self.search != Some(*search) || self.query != query || self.seen > paths.len() {
+            *self = Self {
+                query: query.to_string(),
+                search: Some(*search),
+                ..Self::default()
+            };
+        }
+
+        if self.seen == paths.len() {
+            return;
+        }
+
+        let new = paths.iter().enumerate().skip(self.seen);
+
+        match Matcher::new(search, query) {
+            Ok(matcher) => self.found.extend(new.filter_map(|(index, path)| {
+                let found = matcher.find(&name::display(path.as_os_str()))?;
+                Some((index, found.score, found.positions))
+            })),
+            Err(_) => self
+                .found
+                .extend(new.map(|(index, _)| (index, 0, Vec::new()))),
+        }
+
+        if search.matcher == MatcherKind::Fuzzy {
+            self.found.sort_by_key(|&(_, score, _)| Reverse(score));
+        }
+
+        self.seen = paths.len();
+    }
+}
+
+fn fold(character: char, ignore_case: bool) -> char {
+    if ignore_case {
+        character.to_lowercase().next().unwrap_or(character)
+    } else {
+        character
+    }
+}
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 12;
+const BOUNDARY: i64 = 10;
+const GAP: i64 = 1;
+
+/// Finds the best placement of `pattern` as a subsequence of `text`. Matches
+/// at the start of words and runs of consecutive characters score higher,
+/// and gaps between matched characters cost a little.
+fn fuzzy(pattern: &[char], text: &[char]) -> Option<Match> {
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let boundary = |index: usize| {
+        index == 0
+            || matches!(text[index - 1], ' ' | '-' | '_' | '.' | '/')
+            || (text[index - 1].is_lowercase() && text[index].is_uppercase())
+    };
+
+    // scores[i][j] is the best score with pattern[i] matched at text[j], and
+    // previous[i][j] where pattern[i - 1] was matched for it.
+    let mut scores = vec![vec![None; text.len()]; pattern.len()];
+    let mut previous = vec![vec![0; text.len()]; pattern.len()];
+
+    for (i, &wanted) in pattern.iter().enumerate() {
+        // Best score of the previous row two or more characters before j,
+        // less the gap between it and j - 1.
+        let mut gapped: Option<(i64, usize)> = None;
+
+        for j in 0..text.len() {
+            if i > 0 && j >= 2 {
+                if let Some(score) = scores[i - 1][j - 2] {
+                    if gapped.is_none_or(|(best, _)| score > best) {
+                        gapped = Some((score, j - 2));
+                    }
+                }
+            }
+
+            if text[j] != wanted {
+                gapped = gapped.map(|(best, at)| (best - GAP, at));
+                continue;
+            }
+
+            let bonus = MATCH + if boundary(j) { BOUNDARY } else { 0 };
+
+            let best = if i == 0 {
+                Some((bonus, 0))
+            } else {
+                let adjacent = (j > 0)
+                    .then(|| scores[i - 1][j - 1])
+                    .flatten()
+                    .map(|score| (score + CONSECUTIVE, j - 1));
+                let gap = gapped.map(|(score, at)| (score - GAP, at));
+
+                match (adjacent, gap) {
+                    (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                    (a, b) => a.or(b),
+                }
+            };
+
+            if let Some((score, at)) = best {
+                scores[i][j] = Some(score + if i == 0 { 0 } else { bonus });
+                previous[i][j] = at;
+            }
+
+            gapped = gapped.map(|(best, at)| (best - GAP, at));
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut at, score) = scores[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, Reverse(j)))?;
+
+    let mut positions = vec![at];
+
+    for i in (1..=last).rev() {
+        at = previous[i][at];
+        positions.push(at);
+    }
+
+    positions.reverse();
+
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzzy(pattern: &str, text: &str) -> Option<Match> {
+        let search = Search {
+            matcher: MatcherKind::Fuzzy,
+            smart_case: true,
+        };
+
+        Matcher::new(&search, pattern).unwrap().find(text)
+    }
+
+    #[test]
+    fn fuzzy_matches_characters_in_order() {
+        assert_eq!(fuzzy("fb", "foo_bar").unwrap().positions, [0, 4]);
+        assert!(fuzzy("bf", "foo_bar").is_none());
+        assert!(fuzzy("fbz", "foo_bar").is_none());
+    }
+
+    #[test]
+    fn fuzzy_prefers_word_starts() {
+        assert_eq!(fuzzy("b", "abc_b").unwrap().positions, [4]);
+        assert_eq!(fuzzy("nr", "name_rename").unwrap().positions, [0, 5]);
+        assert_eq!(fuzzy("ml", "MatchList").unwrap().positions, [0, 5]);
+    }
+
+    #[test]
+    fn fuzzy_ranks_consecutive_and_boundary_matches_higher() {
+        let score = |text| fuzzy("abc", text).unwrap().score;
+
+        assert!(score("abc") > score("a_b_c"));
+        assert!(score("a_b_c") > score("axbxc"));
+        assert!(score("axbxc") > score("axxxbxxxc"));
+    }
+
+    #[test]
+    fn fuzzy_follows_smart_case() {
+        assert!(fuzzy("fb", "FooBar").is_some());
+        assert!(fuzzy("FB", "foobar").is_none());
+        assert_eq!(fuzzy("FB", "fooBar FooBar").unwrap().positions, [7, 10]);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        let found = fuzzy("", "anything").unwrap();
+
+        assert_eq!(found.score, 0);
+        assert!(found.positions.is_empty());
+    }
+}
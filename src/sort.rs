@@ -0,0 +1,291 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    io::{Error, ErrorKind, Result},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{entry::Entry, name, xdg};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Name,
+    /// Like name, but runs of digits compare by their value, so `file2`
+    /// comes before `file10`.
+    Natural,
+    /// Largest first.
+    Size,
+    /// Most recently modified first.
+    Modified,
+    Extension,
+    /// Directories, then files, then symbolic links and everything else.
+    Type,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Natural,
+            SortKey::Natural => SortKey::Size,
+            SortKey::Size => SortKey::Modified,
+            SortKey::Modified => SortKey::Extension,
+            SortKey::Extension => SortKey::Type,
+            SortKey::Type => SortKey::Name,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Natural => "natural",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+            SortKey::Extension => "extension",
+            SortKey::Type => "type",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sort {
+    pub by: SortKey,
+    pub reverse: bool,
+    pub directories_first: bool,
+    /// Keep the sort picked for each directory, across sessions.
+    #[serde(skip_serializing)]
+    pub remember: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            by: SortKey::Name,
+            reverse: false,
+            directories_first: false,
+            remember: false,
+        }
+    }
+}
+
+impl Sort {
+    pub fn apply(&self, entries: &mut [Entry]) {
+        match self.by {
+            SortKey::Name => entries.sort_by_key(|e| e.base.file_name()),
+            SortKey::Natural => entries.sort_by(|a, b| {
+                natural(
+                    &a.base.file_name().to_string_lossy(),
+                    &b.base.file_name().to_string_lossy(),
+                )
+            }),
+            SortKey::Size => entries.sort_by_cached_key(|e| {
+                (
+                    Reverse(e.base.metadata().map(|m| m.len()).unwrap_or(0)),
+                    e.base.file_name(),
+                )
+            }),
+            SortKey::Modified => entries.sort_by_cached_key(|e| {
+                (
+                    Reverse(
+                        e.base
+                            .metadata()
+                            .and_then(|m| m.modified())
+                            .unwrap_or(SystemTime::UNIX_EPOCH),
+                    ),
+                    e.base.file_name(),
+                )
+            }),
+            SortKey::Extension => entries.sort_by_cached_key(|e| {
+                let path = e.base.path();
+                (
+                    path.extension().map(|extension| extension.to_os_string()),
+                    e.base.file_name(),
+                )
+            }),
+            SortKey::Type => entries.sort_by_cached_key(|e| {
+                let rank = match e.base.file_type() {
+                    Ok(file_type) if file_type.is_dir() => 0,
+                    Ok(file_type) if file_type.is_file() => 1,
+                    _ => 2,
+                };
+                (rank, e.base.file_name())
+            }),
+        }
+
+        if self.reverse {
+            entries.reverse();
+        }
+
+        if self.directories_first {
+            entries.sort_by_key(|e| !e.base.file_type().is_ok_and(|t| t.is_dir()));
+        }
+    }
+
+    /// Describes the sort for the header, as in `size, reversed`.
+    pub fn label(&self) -> String {
+        let mut label = self.by.name().to_string();
+
+        if self.reverse {
+            label.push_str(", reversed");
+        }
+
+        if self.directories_first {
+            label.push_str(", directories first");
+        }
+
+        label
+    }
+}
+
+/// Compares names with runs of digits ordered by their value, and the rest
+/// ignoring case.
+fn natural(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase()).then(x.cmp(&y));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(characters: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+
+    while let Some(digit) = characters.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+
+    digits
+}
+
+/// The sorts picked for particular directories, kept in
+/// `$XDG_STATE_HOME/nfm/sort.toml`.
+#[derive(Default)]
+pub struct Remembered {
+    sorts: BTreeMap<String, Sort>,
+}
+
+impl Remembered {
+    fn path() -> Option<PathBuf> {
+        xdg::state_home().map(|home| home.join("sort.toml"))
+    }
+
+    /// Loads the remembered sorts. A missing file yields none.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let source = match read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+
+        let sorts = toml::from_str(&source).map_err(|error| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", name::display(path.as_os_str()), error),
+            )
+        })?;
+
+        Ok(Self { sorts })
+    }
+
+    pub fn get(&self, directory: &Path) -> Option<Sort> {
+        self.sorts.get(directory.to_str()?).copied()
+    }
+
+    /// Remembers `sort` for `directory` and saves all of them.
+    pub fn set(&mut self, directory: &Path, sort: Sort) -> Result<()> {
+        let Some(directory) = directory.to_str() else {
+            return Ok(());
+        };
+
+        self.sorts.insert(directory.to_string(), sort);
+
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let source = toml::to_string(&self.sorts)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error.to_string()))?;
+
+        write(path, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_ordered_by_value() {
+        assert_eq!(natural("file2", "file10"), Ordering::Less);
+        assert_eq!(natural("file10", "file9"), Ordering::Greater);
+        assert_eq!(natural("v1.10.0", "v1.9.3"), Ordering::Greater);
+        assert_eq!(natural("2 b", "10 a"), Ordering::Less);
+    }
+
+    #[test]
+    fn leading_zeros_only_break_ties() {
+        assert_eq!(natural("file007", "file8"), Ordering::Less);
+        assert_eq!(natural("file1", "file01"), Ordering::Less);
+        assert_eq!(natural("file01", "file01"), Ordering::Equal);
+    }
+
+    #[test]
+    fn numbers_longer_than_any_integer_are_compared() {
+        assert_eq!(
+            natural("x99999999999999999999999", "x100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn text_ignores_case_before_breaking_ties() {
+        assert_eq!(natural("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural("Apple", "apple"), Ordering::Less);
+        assert_eq!(natural("file", "file1"), Ordering::Less);
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{name, sort::Sort};
+
+/// What a tab remembers about the directory it shows. The state of the tab
+/// in view lives in `NFM` itself and is only stored here while another tab
+/// is shown.
+pub struct Tab {
+    pub path: PathBuf,
+    pub selection: u16,
+    pub scroll: u16,
+    pub search_buffer: String,
+    pub sort: Sort,
+    pub marked: HashSet<OsString>,
+}
+
+impl Tab {
+    pub fn new(path: PathBuf, sort: Sort) -> Self {
+        Self {
+            path,
+            selection: 0,
+            scroll: 0,
+            search_buffer: String::new(),
+            sort,
+            marked: HashSet::new(),
+        }
+    }
+}
+
+/// How a tab showing `path` is labeled in the tab bar.
+pub fn title(path: &Path) -> String {
+    path.file_name()
+        .map(name::display)
+        .unwrap_or_else(|| name::display(path.as_os_str()))
+}
@@ -0,0 +1,95 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `text` takes on the screen.
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// The longest start of `text` that fits in `width` columns, without
+/// splitting a grapheme cluster.
+pub fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+
+        if used > width {
+            return &text[..index];
+        }
+    }
+
+    text
+}
+
+/// `text` cut or padded with spaces to exactly `width` columns.
+pub fn fit(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    format!("{}{}", text, " ".repeat(width - text.width()))
+}
+
+/// Splits `text` into lines of at most `width` columns.
+pub fn wrap(text: &str, width: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let mut line = truncate(rest, width);
+
+        // A grapheme cluster wider than the whole line still gets one.
+        if line.is_empty() {
+            line = rest.graphemes(true).next().unwrap_or(rest);
+        }
+
+        lines.push(line);
+        rest = &rest[line.len()..];
+    }
+
+    lines
+}
+
+/// Byte index of the grapheme cluster before the one at `index`.
+pub fn previous(text: &str, index: usize) -> usize {
+    text[..index]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(previous, _)| previous)
+}
+
+/// Byte index of the grapheme cluster after the one at `index`.
+pub fn next(text: &str, index: usize) -> usize {
+    text[index..]
+        .graphemes(true)
+        .next()
+        .map_or(index, |grapheme| index + grapheme.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_are_never_split() {
+        assert_eq!(truncate("日本語", 5), "日本");
+        assert_eq!(fit("日本語", 5), "日本 ");
+        assert_eq!(fit("ab", 4), "ab  ");
+        assert_eq!(width("e\u{301}👍"), 3);
+    }
+
+    #[test]
+    fn lines_wrap_at_grapheme_boundaries() {
+        assert_eq!(wrap("abcdef", 4), ["abcd", "ef"]);
+        assert_eq!(wrap("日本語", 3), ["日", "本", "語"]);
+        assert_eq!(wrap("日本", 1), ["日", "本"]);
+    }
+
+    #[test]
+    fn neighbours_are_whole_clusters() {
+        let text = "ae\u{301}b";
+
+        assert_eq!(next(text, 1), 4);
+        assert_eq!(previous(text, 4), 1);
+        assert_eq!(previous(text, 0), 0);
+        assert_eq!(next(text, text.len()), text.len());
+    }
+}
@@ -0,0 +1,61 @@
+use crossterm::style::Color;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Current directory in the header and the text of prompts.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub accent: Color,
+    /// Background of the selected entry and of inline prompts.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub selection: Color,
+    /// Entries about to be removed.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub danger: Color,
+    /// Placeholders such as the text of an empty directory.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub muted: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            accent: Color::Blue,
+            selection: Color::Black,
+            danger: Color::Red,
+            muted: Color::Grey,
+        }
+    }
+}
+
+/// Parses a color name such as `dark_blue`, an ANSI value from `0` to `255`
+/// or a `#rrggbb` hex triplet.
+fn parse_color(source: &str) -> Option<Color> {
+    if let Some(hex) = source.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+
+        return Some(Color::Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+
+    if let Ok(value) = source.parse() {
+        return Some(Color::AnsiValue(value));
+    }
+
+    Color::try_from(source).ok()
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let source = String::deserialize(deserializer)?;
+
+    parse_color(&source)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid color `{}`", source)))
+}
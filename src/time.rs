@@ -0,0 +1,51 @@
+use std::{
+    fmt, mem,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A point in time broken down in the local timezone.
+pub struct DateTime {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    pub fn local(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as libc::time_t,
+            Err(error) => -(error.duration().as_secs() as libc::time_t),
+        };
+
+        // SAFETY: `localtime_r` only writes into the `tm` it is given, which
+        // is plain old data and therefore valid when zeroed.
+        let tm = unsafe {
+            let mut tm = mem::zeroed::<libc::tm>();
+            libc::localtime_r(&seconds, &mut tm);
+            tm
+        };
+
+        Self {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u8 + 1,
+            day: tm.tm_mday as u8,
+            hour: tm.tm_hour as u8,
+            minute: tm.tm_min as u8,
+            second: tm.tm_sec as u8,
+        }
+    }
+}
+
+/// Formats as `YYYY-MM-DDThh:mm:ss`, as used by `.trashinfo` files.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,395 @@
+use std::{
+    ffi::{CString, OsString},
+    fs::{
+        copy, create_dir, read_dir, read_link, remove_dir_all, remove_file, rename,
+        set_permissions, symlink_metadata, File, FileTimes, Metadata,
+    },
+    io::{Error, ErrorKind, Result},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{symlink, FileTypeExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use crate::{journal::Operation, name, trash::Trash};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RegisterKind {
+    Copy,
+    Cut,
+}
+
+/// Entries yanked or cut, waiting to be pasted.
+pub struct Register {
+    pub kind: RegisterKind,
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    /// Paste under a free name such as `name (1).ext` next to the existing one.
+    KeepBoth,
+}
+
+/// A paste in progress. Sources are transferred one by one until one of them
+/// collides with an existing entry and no policy has been chosen for it.
+pub struct Paste {
+    pub kind: RegisterKind,
+    pub destination: PathBuf,
+    /// What was done so far, for the journal.
+    pub operations: Vec<Operation>,
+    pending: Vec<PathBuf>,
+    policy: Option<ConflictPolicy>,
+}
+
+impl Paste {
+    pub fn new(register: &Register, destination: PathBuf) -> Self {
+        Self {
+            kind: register.kind,
+            destination,
+            operations: Vec::new(),
+            pending: register.paths.iter().rev().cloned().collect(),
+            policy: None,
+        }
+    }
+
+    /// Transfers as many sources as possible. Returns the path of the entry a
+    /// source collides with when the user has to decide what to do about it.
+    pub fn run(&mut self) -> Result<Option<PathBuf>> {
+        while let Some(source) = self.pending.last() {
+            let target = self.target(source)?;
+
+            if symlink_metadata(&target).is_ok() {
+                let policy = if *source == target {
+                    // Pasting where the entry already is: a copy gets a new
+                    // name, a move has nothing left to do.
+                    match self.kind {
+                        RegisterKind::Copy => ConflictPolicy::KeepBoth,
+                        RegisterKind::Cut => ConflictPolicy::Skip,
+                    }
+                } else if let Some(policy) = self.policy {
+                    policy
+                } else {
+                    return Ok(Some(target));
+                };
+
+                self.apply(policy)?;
+            } else {
+                let source = self.pending.pop().unwrap();
+                self.transfer(&source, &target)?;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Settles the pending conflict with `policy`, remembering it for the
+    /// following ones if `all` is set, and carries on with the paste.
+    pub fn resolve(&mut self, policy: ConflictPolicy, all: bool) -> Result<Option<PathBuf>> {
+        if all {
+            self.policy = Some(policy);
+        }
+
+        self.apply(policy)?;
+        self.run()
+    }
+
+    fn apply(&mut self, policy: ConflictPolicy) -> Result<()> {
+        let Some(source) = self.pending.pop() else {
+            return Ok(());
+        };
+
+        let target = self.target(&source)?;
+
+        match policy {
+            ConflictPolicy::Skip => Ok(()),
+
+            ConflictPolicy::Overwrite => {
+                if source == target {
+                    return Ok(());
+                }
+
+                // The entry being replaced goes to the trash, which keeps the
+                // paste undoable.
+                let item = Trash::home()?.put(&target)?;
+                self.operations.push(Operation::trash(item)?);
+                self.transfer(&source, &target)
+            }
+
+            ConflictPolicy::KeepBoth => self.transfer(&source, &unique_path(&target)),
+        }
+    }
+
+    fn target(&self, source: &Path) -> Result<PathBuf> {
+        let name = source.file_name().ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("cannot paste {}", name::display(source.as_os_str())),
+            )
+        })?;
+
+        Ok(self.destination.join(name))
+    }
+
+    fn transfer(&mut self, source: &Path, target: &Path) -> Result<()> {
+        if target.starts_with(source) && target != source {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "cannot paste {} into itself",
+                    name::display(source.as_os_str())
+                ),
+            ));
+        }
+
+        match self.kind {
+            RegisterKind::Copy => {
+                copy_recursive(source, target)?;
+
+                self.operations
+                    .push(Operation::copy(source.to_path_buf(), target.to_path_buf())?);
+            }
+
+            RegisterKind::Cut => {
+                move_path(source, target)?;
+
+                self.operations.push(Operation::moved(
+                    source.to_path_buf(),
+                    target.to_path_buf(),
+                )?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the entry at `path`, recursing into it if it is a directory.
+pub fn remove(path: &Path) -> Result<()> {
+    if symlink_metadata(path)?.is_dir() {
+        remove_dir_all(path)
+    } else {
+        remove_file(path)
+    }
+}
+
+/// Copies `from` to `to`, recursing into directories. Symbolic links and
+/// named pipes are recreated rather than followed or read, and permissions
+/// and timestamps are kept. Devices and sockets cannot be copied.
+pub fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    let metadata = symlink_metadata(from)?;
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        return symlink(read_link(from)?, to);
+    }
+
+    if file_type.is_fifo() {
+        return make_fifo(to, metadata.permissions().mode());
+    }
+
+    if !file_type.is_dir() && !file_type.is_file() {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "{} is a device or socket and cannot be copied",
+                name::display(from.as_os_str())
+            ),
+        ));
+    }
+
+    if file_type.is_dir() {
+        create_dir(to)?;
+
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        copy_times(&metadata, to)?;
+        set_permissions(to, metadata.permissions())
+    } else {
+        copy(from, to)?;
+        copy_times(&metadata, to)
+    }
+}
+
+fn make_fifo(path: &Path, mode: u32) -> Result<()> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+
+    if unsafe { libc::mkfifo(path.as_ptr(), (mode & 0o7777) as libc::mode_t) } != 0 {
+        return Err(Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn copy_times(metadata: &Metadata, to: &Path) -> Result<()> {
+    File::open(to)?.set_times(
+        FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?),
+    )
+}
+
+/// Moves `from` to `to`, falling back to copying and removing the original
+/// when they live on different filesystems.
+pub fn move_path(from: &Path, to: &Path) -> Result<()> {
+    match rename(from, to) {
+        Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+            copy_recursive(from, to)?;
+            remove(from)
+        }
+        result => result,
+    }
+}
+
+/// The first of `name (1).ext`, `name (2).ext`, ... next to `path` that does
+/// not exist yet.
+pub fn unique_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    let extension = path.extension();
+
+    (1..)
+        .map(|number| {
+            let mut name = OsString::from(stem);
+            name.push(format!(" ({})", number));
+
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+
+            path.with_file_name(name)
+        })
+        .find(|candidate| symlink_metadata(candidate).is_err())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        fs::{self, read_to_string},
+        process,
+    };
+
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("nfm-test-{}-{}", name, process::id()));
+        let _ = remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("from")).unwrap();
+        fs::create_dir_all(directory.join("to")).unwrap();
+        directory
+    }
+
+    fn register(kind: RegisterKind, directory: &Path, names: &[&str]) -> Register {
+        Register {
+            kind,
+            paths: names
+                .iter()
+                .map(|name| {
+                    let path = directory.join("from").join(name);
+                    fs::write(&path, format!("new {}", name)).unwrap();
+                    path
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn pastes_stop_at_each_conflict_until_resolved() {
+        let directory = scratch("paste-conflict");
+        fs::write(directory.join("to/b"), "old b").unwrap();
+        fs::write(directory.join("to/c"), "old c").unwrap();
+
+        let register = register(RegisterKind::Copy, &directory, &["a", "b", "c"]);
+        let mut paste = Paste::new(&register, directory.join("to"));
+
+        assert_eq!(paste.run().unwrap(), Some(directory.join("to/b")));
+        assert_eq!(
+            paste.resolve(ConflictPolicy::Skip, false).unwrap(),
+            Some(directory.join("to/c"))
+        );
+        assert_eq!(
+            paste.resolve(ConflictPolicy::KeepBoth, false).unwrap(),
+            None
+        );
+
+        assert_eq!(read_to_string(directory.join("to/a")).unwrap(), "new a");
+        assert_eq!(read_to_string(directory.join("to/b")).unwrap(), "old b");
+        assert_eq!(read_to_string(directory.join("to/c")).unwrap(), "old c");
+        assert_eq!(read_to_string(directory.join("to/c (1)")).unwrap(), "new c");
+        assert_eq!(paste.operations.len(), 2);
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn policies_for_all_settle_the_remaining_conflicts() {
+        let directory = scratch("paste-all");
+        fs::write(directory.join("to/a.txt"), "old a").unwrap();
+        fs::write(directory.join("to/b.txt"), "old b").unwrap();
+        fs::write(directory.join("to/b (1).txt"), "old b (1)").unwrap();
+
+        let register = register(RegisterKind::Cut, &directory, &["a.txt", "b.txt"]);
+        let mut paste = Paste::new(&register, directory.join("to"));
+
+        assert_eq!(paste.run().unwrap(), Some(directory.join("to/a.txt")));
+        assert_eq!(paste.resolve(ConflictPolicy::KeepBoth, true).unwrap(), None);
+
+        assert_eq!(
+            read_to_string(directory.join("to/a (1).txt")).unwrap(),
+            "new a.txt"
+        );
+        assert_eq!(
+            read_to_string(directory.join("to/b (2).txt")).unwrap(),
+            "new b.txt"
+        );
+        assert!(symlink_metadata(directory.join("from/a.txt")).is_err());
+        assert!(symlink_metadata(directory.join("from/b.txt")).is_err());
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn pasting_where_entries_already_are_needs_no_answer() {
+        let directory = scratch("paste-same");
+
+        let copy = register(RegisterKind::Copy, &directory, &["a"]);
+        let mut paste = Paste::new(&copy, directory.join("from"));
+        assert_eq!(paste.run().unwrap(), None);
+        assert_eq!(
+            read_to_string(directory.join("from/a (1)")).unwrap(),
+            "new a"
+        );
+
+        let cut = register(RegisterKind::Cut, &directory, &["b"]);
+        let mut paste = Paste::new(&cut, directory.join("from"));
+        assert_eq!(paste.run().unwrap(), None);
+        assert!(paste.operations.is_empty());
+        assert!(symlink_metadata(directory.join("from/b (1)")).is_err());
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn directories_cannot_be_pasted_into_themselves() {
+        let directory = scratch("paste-into");
+        let register = Register {
+            kind: RegisterKind::Copy,
+            paths: vec![directory.join("from")],
+        };
+
+        let mut paste = Paste::new(&register, directory.join("from"));
+        assert!(paste.run().is_err());
+        assert!(symlink_metadata(directory.join("from/from")).is_err());
+
+        remove_dir_all(&directory).unwrap();
+    }
+}
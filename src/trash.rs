@@ -0,0 +1,300 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fs::{create_dir_all, read_dir, read_to_string, remove_file, symlink_metadata, OpenOptions},
+    io::{Error, ErrorKind, Result, Write},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{name, time::DateTime, transfer, xdg};
+
+/// An entry of the trash, described by its `.trashinfo` file.
+pub struct TrashItem {
+    /// Name of the entry under `files` and, with `.trashinfo` appended, of
+    /// its description under `info`.
+    pub name: OsString,
+    pub original: PathBuf,
+    pub deleted: String,
+}
+
+/// The home trash described by the FreeDesktop trash specification.
+pub struct Trash {
+    root: PathBuf,
+}
+
+impl Trash {
+    /// The trash at `$XDG_DATA_HOME/Trash`.
+    pub fn home() -> Result<Self> {
+        let root = xdg::data_home()
+            .map(|home| home.join("Trash"))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "cannot locate the trash directory"))?;
+
+        Ok(Self { root })
+    }
+
+    fn files(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    fn info(&self, name: &OsStr) -> PathBuf {
+        let mut file_name = name.to_os_string();
+        file_name.push(".trashinfo");
+        self.root.join("info").join(file_name)
+    }
+
+    /// Moves `path` to the trash, recording where it came from.
+    pub fn put(&self, path: &Path) -> Result<TrashItem> {
+        let original = std::path::absolute(path)?;
+        let base_name = original
+            .file_name()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("cannot trash {}", name::display(original.as_os_str())),
+                )
+            })?
+            .to_os_string();
+
+        create_dir_all(self.files())?;
+        create_dir_all(self.root.join("info"))?;
+
+        let deleted = DateTime::local(SystemTime::now()).to_string();
+
+        // The info file is created first and exclusively, which reserves the
+        // name against other programs trashing at the same time.
+        for number in 1.. {
+            let mut name = base_name.clone();
+
+            if number > 1 {
+                name.push(format!(".{}", number));
+            }
+
+            if symlink_metadata(self.files().join(&name)).is_ok() {
+                continue;
+            }
+
+            let mut info = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(self.info(&name))
+            {
+                Ok(info) => info,
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            };
+
+            write!(
+                info,
+                "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                encode(original.as_os_str()),
+                deleted
+            )?;
+
+            if let Err(error) = transfer::move_path(&original, &self.files().join(&name)) {
+                remove_file(self.info(&name))?;
+                return Err(error);
+            }
+
+            return Ok(TrashItem {
+                name,
+                original,
+                deleted,
+            });
+        }
+
+        unreachable!()
+    }
+
+    /// The items in the trash, most recently deleted first.
+    pub fn items(&self) -> Result<Vec<TrashItem>> {
+        let directory = match read_dir(self.root.join("info")) {
+            Ok(directory) => directory,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut items = Vec::new();
+
+        for entry in directory {
+            let path = entry?.path();
+
+            if path.extension() != Some(OsStr::new("trashinfo")) {
+                continue;
+            }
+
+            let Some(name) = path.file_stem().map(OsStr::to_os_string) else {
+                continue;
+            };
+
+            let Ok(source) = read_to_string(&path) else {
+                continue;
+            };
+
+            let mut original = None;
+            let mut deleted = String::new();
+
+            for line in source.lines() {
+                if let Some(value) = line.strip_prefix("Path=") {
+                    original = Some(PathBuf::from(decode(value)));
+                } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+                    deleted = value.to_string();
+                }
+            }
+
+            // Relative paths are relative to the directory holding the trash.
+            if let Some(original) = original {
+                items.push(TrashItem {
+                    name,
+                    original: self.root.parent().unwrap_or(&self.root).join(original),
+                    deleted,
+                });
+            }
+        }
+
+        items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+        Ok(items)
+    }
+
+    /// Where `item` is kept in the trash.
+    pub fn file(&self, item: &TrashItem) -> PathBuf {
+        self.files().join(&item.name)
+    }
+
+    /// Whether `item` is still in the trash.
+    pub fn contains(&self, item: &TrashItem) -> bool {
+        symlink_metadata(self.file(item)).is_ok() && symlink_metadata(self.info(&item.name)).is_ok()
+    }
+
+    /// Puts `item` back where it was deleted from.
+    pub fn restore(&self, item: &TrashItem) -> Result<()> {
+        if symlink_metadata(&item.original).is_ok() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!(
+                    "{} already exists",
+                    name::display(item.original.as_os_str())
+                ),
+            ));
+        }
+
+        if let Some(parent) = item.original.parent() {
+            create_dir_all(parent)?;
+        }
+
+        transfer::move_path(&self.file(item), &item.original)?;
+        remove_file(self.info(&item.name))
+    }
+
+    /// Deletes `item` for good.
+    pub fn purge(&self, item: &TrashItem) -> Result<()> {
+        match transfer::remove(&self.file(item)) {
+            Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+
+        remove_file(self.info(&item.name))
+    }
+}
+
+/// Percent-encodes a path the way `.trashinfo` files store it.
+fn encode(path: &OsStr) -> String {
+    path.as_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn decode(value: &str) -> OsString {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+
+        if byte == b'%' {
+            if let Some(value) = value
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(value);
+                index += 3;
+                continue;
+            }
+        }
+
+        decoded.push(byte);
+        index += 1;
+    }
+
+    OsString::from_vec(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn paths_are_percent_encoded_and_decoded() {
+        let path = OsStr::from_bytes(b"/tmp/a b%c/caf\xc3\xa9\xff.txt");
+
+        assert_eq!(encode(path), "/tmp/a%20b%25c/caf%C3%A9%FF.txt");
+        assert_eq!(decode(&encode(path)), path);
+        assert_eq!(decode("%2fa%zz%4"), OsStr::new("/a%zz%4"));
+    }
+
+    #[test]
+    fn items_are_restored_where_they_were() {
+        let directory = env::temp_dir().join(format!("nfm-test-trash-{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("home/some dir")).unwrap();
+
+        let trash = Trash {
+            root: directory.join("Trash"),
+        };
+        let original = directory.join("home/some dir/notes.txt");
+
+        fs::write(&original, "first").unwrap();
+        let first = trash.put(&original).unwrap();
+        fs::write(&original, "second").unwrap();
+        let second = trash.put(&original).unwrap();
+
+        assert_eq!(first.name, "notes.txt");
+        assert_eq!(second.name, "notes.txt.2");
+
+        let items = trash.items().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.original == original));
+
+        fs::remove_dir_all(directory.join("home")).unwrap();
+        trash.restore(&second).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "second");
+        assert!(!trash.contains(&second));
+
+        assert!(trash.restore(&first).is_err());
+        assert!(trash.contains(&first));
+
+        trash.purge(&first).unwrap();
+        assert!(trash.items().unwrap().is_empty());
+
+        // Other programs may store paths relative to the trash's parent.
+        fs::write(
+            trash.info(OsStr::new("old")),
+            "[Trash Info]\nPath=home/old%20file\nDeletionDate=2020-01-01T00:00:00\n",
+        )
+        .unwrap();
+        let items = trash.items().unwrap();
+        assert_eq!(items[0].original, directory.join("home/old file"));
+        assert_eq!(items[0].deleted, "2020-01-01T00:00:00");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    ffi::{CStr, CString},
+    io::{Error, ErrorKind, Result},
+    mem, ptr,
+};
+
+/// Size of the buffers first handed to the `getpw*_r` and `getgr*_r`
+/// functions, which is plenty for most entries. It is doubled while an entry
+/// does not fit, as a group with many members may not, up to `MAX_BUFFER`.
+const BUFFER: usize = 4096;
+
+const MAX_BUFFER: usize = 1 << 20;
+
+pub fn user_name(uid: u32) -> Option<String> {
+    // SAFETY: `getpwuid_r` is handed valid pointers and the size of the
+    // buffer behind them.
+    let (passwd, _buffer) = lookup(|passwd, buffer, size, result| unsafe {
+        libc::getpwuid_r(uid, passwd, buffer, size, result)
+    })?;
+
+    // SAFETY: the name lives in `_buffer`, which outlives this read of it.
+    Some(
+        unsafe { CStr::from_ptr(passwd.pw_name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+pub fn group_name(gid: u32) -> Option<String> {
+    // SAFETY: as in `user_name`.
+    let (group, _buffer) = lookup(|group, buffer, size, result| unsafe {
+        libc::getgrgid_r(gid, group, buffer, size, result)
+    })?;
+
+    // SAFETY: as in `user_name`.
+    Some(
+        unsafe { CStr::from_ptr(group.gr_name) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Looks up the user called `name`, which may also be a plain uid.
+pub fn uid(name: &str) -> Result<u32> {
+    let c_name = c_name(name)?;
+
+    // SAFETY: as in `user_name`, and `c_name` is a valid C string.
+    if let Some((passwd, _)) = lookup(|passwd, buffer, size, result| unsafe {
+        libc::getpwnam_r(c_name.as_ptr(), passwd, buffer, size, result)
+    }) {
+        return Ok(passwd.pw_uid);
+    }
+
+    name.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("there is no user called {}", name),
+        )
+    })
+}
+
+/// Looks up the group called `name`, which may also be a plain gid.
+pub fn gid(name: &str) -> Result<u32> {
+    let c_name = c_name(name)?;
+
+    // SAFETY: as in `uid`.
+    if let Some((group, _)) = lookup(|group, buffer, size, result| unsafe {
+        libc::getgrnam_r(c_name.as_ptr(), group, buffer, size, result)
+    }) {
+        return Ok(group.gr_gid);
+    }
+
+    name.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("there is no group called {}", name),
+        )
+    })
+}
+
+fn c_name(name: &str) -> Result<CString> {
+    CString::new(name).map_err(|error| Error::new(ErrorKind::InvalidInput, error))
+}
+
+/// Runs one of the `get*_r` functions, growing the buffer while the entry
+/// does not fit in it. Returns the entry found, if any, along with the buffer
+/// its strings point into.
+///
+/// `T` must be `libc::passwd` or `libc::group`.
+fn lookup<T>(
+    mut get: impl FnMut(*mut T, *mut libc::c_char, usize, *mut *mut T) -> libc::c_int,
+) -> Option<(T, Vec<libc::c_char>)> {
+    let mut size = BUFFER;
+
+    loop {
+        let mut buffer = vec![0 as libc::c_char; size];
+        let mut result = ptr::null_mut();
+
+        // SAFETY: `passwd` and `group` are plain old data, valid when zeroed.
+        let mut entry = unsafe { mem::zeroed::<T>() };
+
+        let error = get(&mut entry, buffer.as_mut_ptr(), size, &mut result);
+
+        if error == libc::ERANGE && size < MAX_BUFFER {
+            size *= 2;
+            continue;
+        }
+
+        // Moving the buffer leaves its contents where they are, so the
+        // strings of the entry stay valid.
+        return (!result.is_null()).then_some((entry, buffer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_that_do_not_fit_are_looked_up_again() {
+        let mut sizes = Vec::new();
+        let found = lookup::<libc::group>(|group, _, size, result| {
+            sizes.push(size);
+
+            if size < 4 * BUFFER {
+                return libc::ERANGE;
+            }
+
+            // SAFETY: both pointers come from `lookup` and are valid.
+            unsafe { *result = group };
+            0
+        });
+
+        assert!(found.is_some_and(|(_, buffer)| buffer.len() == 4 * BUFFER));
+        assert_eq!(sizes, [BUFFER, 2 * BUFFER, 4 * BUFFER]);
+    }
+
+    #[test]
+    fn growing_stops_at_the_cap() {
+        let mut calls = 0;
+        let found = lookup::<libc::passwd>(|_, _, _, _| {
+            calls += 1;
+            libc::ERANGE
+        });
+
+        assert!(found.is_none());
+        assert_eq!(BUFFER << (calls - 1), MAX_BUFFER);
+    }
+
+    #[test]
+    fn names_and_ids_are_looked_up() {
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+        assert_eq!(uid("root").unwrap(), 0);
+        assert_eq!(gid("0").unwrap(), 0);
+        assert_eq!(
+            uid("nfm-no-such-user").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use ignore::{DirEntry, WalkBuilder, WalkState};
+
+/// How often streaming results are worth a redraw.
+const REFRESH: Duration = Duration::from_millis(100);
+
+/// A walk of the tree below `root` running in the background, collecting
+/// what is found along the way in no particular order.
+pub struct Walk<T> {
+    pub root: PathBuf,
+    pub items: Vec<T>,
+    pub done: bool,
+    receiver: Receiver<T>,
+    /// How many items there were, and when, as of the last redraw.
+    reported: (usize, Instant),
+}
+
+impl<T: Send + 'static> Walk<T> {
+    /// Starts walking `root` on several threads, skipping what `.gitignore`
+    /// and `.ignore` files exclude, and hidden entries unless `show_hidden`
+    /// is set. `visit` is given each entry and its path relative to `root`,
+    /// and returns what it found there. The walk stops on its own once the
+    /// `Walk` is dropped.
+    pub fn spawn<F>(root: PathBuf, show_hidden: bool, visit: F) -> Self
+    where
+        F: Fn(&DirEntry, &Path) -> Vec<T> + Send + Sync + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let walker = WalkBuilder::new(&root)
+            .hidden(!show_hidden)
+            .require_git(false)
+            .build_parallel();
+        let prefix = root.clone();
+        let visit = Arc::new(visit);
+
+        thread::spawn(move || {
+            walker.run(|| {
+                let sender = sender.clone();
+                let prefix = prefix.clone();
+                let visit = visit.clone();
+
+                Box::new(move |entry| {
+                    let Ok(entry) = entry else {
+                        return WalkState::Continue;
+                    };
+
+                    let Ok(relative) = entry.path().strip_prefix(&prefix) else {
+                        return WalkState::Continue;
+                    };
+
+                    if relative.as_os_str().is_empty() {
+                        return WalkState::Continue;
+                    }
+
+                    for item in visit(&entry, relative) {
+                        if sender.send(item).is_err() {
+                            return WalkState::Quit;
+                        }
+                    }
+
+                    WalkState::Continue
+                })
+            })
+        });
+
+        Self {
+            root,
+            items: Vec::new(),
+            done: false,
+            receiver,
+            reported: (0, Instant::now()),
+        }
+    }
+
+    /// Collects the items found since the last call. Returns whether the
+    /// results changed enough to be redrawn.
+    pub fn poll(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(item) => self.items.push(item),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    return true;
+                }
+            }
+        }
+
+        if self.items.len() > self.reported.0 && self.reported.1.elapsed() >= REFRESH {
+            self.reported = (self.items.len(), Instant::now());
+            return true;
+        }
+
+        false
+    }
+}
@@ -0,0 +1,23 @@
+use std::{env, path::PathBuf};
+
+fn base_directory(variable: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+}
+
+/// `$XDG_CONFIG_HOME/nfm`, falling back to `~/.config/nfm`.
+pub fn config_home() -> Option<PathBuf> {
+    base_directory("XDG_CONFIG_HOME", ".config").map(|path| path.join("nfm"))
+}
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> Option<PathBuf> {
+    base_directory("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_STATE_HOME/nfm`, falling back to `~/.local/state/nfm`.
+pub fn state_home() -> Option<PathBuf> {
+    base_directory("XDG_STATE_HOME", ".local/state").map(|path| path.join("nfm"))
+}